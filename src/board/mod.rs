@@ -1,9 +1,11 @@
+use crate::grid::round_to_multiple;
 use crate::instance::InstanceManager;
 use crate::viewport::Viewport;
 use crate::GraphicsContext;
 use bytemuck::{Pod, Zeroable};
 use glam::IVec2;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::num::NonZeroU32;
 use wgpu::util::DeviceExt;
 
@@ -101,6 +103,7 @@ pub struct BoardRenderer {
     index_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     instances: InstanceManager<Instance>,
+    clear_color: wgpu::Color,
 }
 
 impl BoardRenderer {
@@ -256,6 +259,12 @@ impl BoardRenderer {
             index_buffer,
             bind_group,
             instances,
+            clear_color: wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
         }
     }
 
@@ -264,6 +273,27 @@ impl BoardRenderer {
         Handle { inner }
     }
 
+    /// Sets the background color drawn behind all boards.
+    pub fn update_clear_color(&mut self, color: [f32; 4]) {
+        let [r, g, b, a] = color.map(f64::from);
+        self.clear_color = wgpu::Color { r, g, b, a };
+    }
+
+    /// Replaces the board drawn for `handle`, keeping its place in the draw order.
+    pub fn update(&mut self, handle: &Handle, board: &Board) {
+        handle.set(board);
+    }
+
+    /// Swaps the draw order of two boards, see [`InstanceManager::swap`].
+    pub fn swap(&mut self, a: &Handle, b: &Handle) -> bool {
+        self.instances.swap(&a.inner, &b.inner)
+    }
+
+    pub fn remove(&mut self, handle: Handle) {
+        // Dropping the instance handle queues its removal.
+        drop(handle);
+    }
+
     pub fn draw(
         &mut self,
         viewport: &Viewport,
@@ -283,12 +313,7 @@ impl BoardRenderer {
                 view: &frame_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
+                    load: wgpu::LoadOp::Clear(self.clear_color),
                     store: true,
                 },
             })],
@@ -316,9 +341,89 @@ impl BoardRenderer {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Board {
     pub position: IVec2,
     pub size: IVec2,
     pub color: [f32; 4],
     pub z_index: u32,
 }
+
+impl Board {
+    /// A white board at the bottom of the stack. Use the `with_*` methods to set the other
+    /// fields.
+    pub fn new(position: IVec2, size: IVec2) -> Board {
+        Board {
+            position,
+            size,
+            color: [1.0; 4],
+            z_index: 0,
+        }
+    }
+
+    pub fn with_color(self, color: [f32; 4]) -> Board {
+        Board { color, ..self }
+    }
+
+    pub fn with_z_index(self, z_index: u32) -> Board {
+        Board { z_index, ..self }
+    }
+
+    /// The number of tiles covered by the board.
+    pub fn area(&self) -> u64 {
+        let size = self.size.max(IVec2::ZERO);
+        size.x as u64 * size.y as u64
+    }
+
+    pub fn contains(&self, position: IVec2) -> bool {
+        position.cmpge(self.position).all() && position.cmplt(self.position + self.size).all()
+    }
+
+    /// Rounds the position and size to the nearest multiple of `grid`, on each axis.
+    ///
+    /// The size is never rounded below one grid cell.
+    pub fn snap_to_grid(&self, grid: IVec2) -> Board {
+        let grid = grid.max(IVec2::ONE);
+        Board {
+            position: round_to_multiple(self.position, grid),
+            size: round_to_multiple(self.size, grid).max(grid),
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Board;
+    use glam::IVec2;
+
+    fn board(position: IVec2, size: IVec2) -> Board {
+        Board::new(position, size).with_z_index(1)
+    }
+
+    #[test]
+    fn snap_to_grid() {
+        let snapped = board(IVec2::new(5, -7), IVec2::new(10, 1)).snap_to_grid(IVec2::new(4, 8));
+        assert_eq!(snapped.position, IVec2::new(4, -8));
+        assert_eq!(snapped.size, IVec2::new(12, 8));
+        assert_eq!(snapped.z_index, 1);
+    }
+
+    #[test]
+    fn area() {
+        assert_eq!(board(IVec2::new(-3, 2), IVec2::new(5, 7)).area(), 35);
+        assert_eq!(
+            board(IVec2::ZERO, IVec2::new(100_000, 100_000)).area(),
+            10_000_000_000
+        );
+        assert_eq!(board(IVec2::ZERO, IVec2::new(-1, 4)).area(), 0);
+    }
+
+    #[test]
+    fn snap_to_unit_grid_is_identity() {
+        let original = board(IVec2::new(-3, 2), IVec2::new(5, 7));
+        let snapped = original.snap_to_grid(IVec2::ONE);
+        assert_eq!(snapped.position, original.position);
+        assert_eq!(snapped.size, original.size);
+    }
+}
@@ -1,43 +1,71 @@
 use crate::board::{self, BoardRenderer};
 use crate::depot::{self, Depot};
-use crate::direction::{Direction, Relative};
-use crate::rect::{self, Color, RectRenderer, WireConnection};
+use crate::direction::{Axis, Direction, Relative};
+use crate::gate_layout;
+use crate::grid::GridAligner;
+use crate::journal::{self, Change, CircuitEdit, EditTarget};
+use crate::logic_gate::{Gate, GateKind};
+use crate::profiler;
+use crate::rect::{self, Color, PinKind, RectRenderer, WireConnection};
+use crate::selection::Selection;
+use crate::serializer::{CircuitData, ComponentRecord, RectI, WireRecord};
 use crate::simulation::Simulation;
+use crate::synthesis::{self, TruthTable};
+use crate::theme::Theme;
 use crate::viewport::Viewport;
 use crate::GraphicsContext;
-use glam::{IVec2, Vec4};
+use anyhow::{bail, ensure, Context};
+use glam::{IVec2, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io::Write;
+use std::path::Path;
 use std::rc::Rc;
 
 pub struct Circuit {
     board_renderer: BoardRenderer,
     rect_renderer: RectRenderer,
-    _root_board: board::Handle,
+    root_board: board::Handle,
+    boards: Vec<(board::Handle, board::Board)>,
     tiles: HashMap<IVec2, Tile>,
     components: Depot<Component>,
     wires: Depot<Wire>,
     simulation: Simulation,
+    net_names: HashMap<NetId, String>,
+    /// Aligns boards placed with [`Self::place_board`].
+    grid: GridAligner,
+    /// The board color of the current theme, used for boards placed by the circuit itself.
+    board_color: [f32; 4],
+    /// Whether components or wires changed since the circuit was last saved or loaded.
+    dirty: bool,
+    /// Whether wires are drawn dashed, see [`Self::set_clock_net_display`].
+    clock_net_display: bool,
+    /// The changes made since [`Self::start_journal`], if recording.
+    journal: Option<Vec<Change>>,
 }
 
 impl Circuit {
     pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> Self {
         let mut board_renderer = BoardRenderer::new(gfx, viewport);
-        let _root_board = board_renderer.insert(&board::Board {
-            position: IVec2::new(-10_000, -10_000),
-            size: IVec2::new(20_000, 20_000),
-            color: [0.1, 0.1, 0.1, 1.0],
-            z_index: 0,
-        });
+        let board_color = [0.1, 0.1, 0.1, 1.0];
+        let root_board = board_renderer.insert(&make_root_board(board_color));
 
         Self {
             board_renderer,
             rect_renderer: RectRenderer::new(gfx, viewport),
-            _root_board,
+            root_board,
+            boards: Vec::new(),
             tiles: HashMap::new(),
             components: Depot::new(),
             wires: Depot::new(),
             simulation: Simulation::new(),
+            net_names: HashMap::new(),
+            grid: GridAligner::default(),
+            board_color,
+            dirty: false,
+            clock_net_display: false,
+            journal: None,
         }
     }
 
@@ -49,27 +77,609 @@ impl Circuit {
         depth_view: &wgpu::TextureView,
     ) {
         // XXX
-        self.simulation.tick();
-        self.rect_renderer.update_cluster_states(&self.simulation);
+        if !self.simulation.is_stable() {
+            self.step();
+        }
 
         self.board_renderer
             .draw(viewport, encoder, frame_view, depth_view);
+        if self.clock_net_display {
+            self.rect_renderer.draw_dashed(
+                CLOCK_NET_DASH_LENGTH,
+                CLOCK_NET_GAP_LENGTH,
+                viewport,
+                encoder,
+                frame_view,
+                depth_view,
+            );
+        } else {
+            self.rect_renderer
+                .draw(viewport, encoder, frame_view, depth_view);
+        }
+    }
+
+    /// Draws every wire dashed when enabled, to make signals that toggle in step, like clocks,
+    /// easier to follow against the solid components. See [`RectRenderer::draw_dashed`].
+    pub fn set_clock_net_display(&mut self, enabled: bool) {
+        self.clock_net_display = enabled;
+    }
+
+    pub fn clock_net_display(&self) -> bool {
+        self.clock_net_display
+    }
+
+    /// Advances the simulation by one tick and shows the result. Returns the number of nets whose
+    /// power changed; 0 once the circuit has settled.
+    pub fn step(&mut self) -> usize {
+        let _span = profiler::span("Simulation::tick");
+        let changed = self.simulation.tick();
+        self.rect_renderer.update_cluster_states(&self.simulation);
+        changed
+    }
+
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        self.rect_renderer.set_wire_color(&theme.wire_color);
+        self.board_renderer.update_clear_color(theme.clear_color);
+        self.root_board.set(&make_root_board(theme.board_color));
+        self.board_color = theme.board_color;
+    }
+
+    /// Fades the components and wires, see [`RectRenderer::set_global_alpha`].
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        self.rect_renderer.set_global_alpha(alpha);
+    }
+
+    /// See [`RectRenderer::set_wireframe_mode`].
+    pub fn set_wireframe_mode(&mut self, enabled: bool) -> bool {
+        self.rect_renderer.set_wireframe_mode(enabled)
+    }
+
+    /// The number of drawn wire segments and pins, see [`RectRenderer::wire_count`].
+    pub fn wire_count(&self) -> usize {
+        self.rect_renderer.wire_count()
+    }
+
+    /// How many of [`Self::wire_count`] are currently drawn powered.
+    pub fn wire_count_powered(&self) -> usize {
+        self.rect_renderer.wire_count_powered()
+    }
+
+    /// The share of [`Self::wire_count`] drawn powered, from 0 to 100. An empty circuit is at 0.
+    pub fn net_power_percentage(&self) -> f32 {
+        match self.wire_count() {
+            0 => 0.0,
+            wire_count => 100.0 * self.wire_count_powered() as f32 / wire_count as f32,
+        }
+    }
+
+    /// Whether the components or wires changed since the last call to [`Self::mark_saved`].
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.dirty
+    }
+
+    /// Marks the current state as matching the one on disk, after saving or loading.
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Snapshots the layout of the circuit, sorted by position.
+    pub fn to_data(&self) -> CircuitData {
+        let mut components: Vec<ComponentRecord> = self
+            .components
+            .iter()
+            .map(|(_, component)| component.record())
+            .collect();
+        components.sort_by_key(|component| <[i32; 2]>::from(component.position));
+        let mut wires: Vec<WireRecord> = self
+            .wires
+            .iter()
+            .map(|(_, wire)| WireRecord {
+                start: wire.start,
+                end: wire.end,
+            })
+            .collect();
+        wires.sort_by_key(|wire| (<[i32; 2]>::from(wire.start), <[i32; 2]>::from(wire.end)));
+        let boards = self.boards.iter().map(|(_handle, board)| *board).collect();
+        CircuitData {
+            components,
+            wires,
+            boards,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the contents of the circuit, boards included, with `data`.
+    ///
+    /// Components and wires that cannot be placed are skipped. Boards are restored as saved,
+    /// without grid alignment.
+    pub fn load_data(&mut self, data: &CircuitData) {
+        self.clear();
+        for (handle, _board) in self.boards.drain(..) {
+            self.board_renderer.remove(handle);
+        }
+        for board in &data.boards {
+            let handle = self.board_renderer.insert(board);
+            self.boards.push((handle, *board));
+        }
+        // Every component and wire needs at least one rect.
         self.rect_renderer
-            .draw(viewport, encoder, frame_view, depth_view);
+            .reserve(data.components.len() + data.wires.len());
+        self.insert_data(data);
+        self.rect_renderer.defragment();
+    }
+
+    /// The contents of the circuit covered by `board`, relative to its position.
+    pub fn copy_region(&self, board: &board::Board) -> CircuitData {
+        self.serialize_partial(RectI {
+            min: board.position,
+            max: board.position + board.size,
+        })
+    }
+
+    /// The contents of the circuit inside `region`, relative to `region.min`. See
+    /// [`CircuitData::region`].
+    pub fn serialize_partial(&self, region: RectI) -> CircuitData {
+        self.to_data().region(region)
+    }
+
+    /// Extracts the component at `root` and everything connected to it through at most `depth`
+    /// nets, relative to `root` so that [`Self::import_merge`] puts it back at its origin.
+    ///
+    /// Each level adds the nets touching the components found so far, with their wires, and every
+    /// component on them. `depth = 0` extracts only the component at `root`, `u32::MAX` its whole
+    /// connected part of the circuit. Empty if there is no component at `root`.
+    pub fn clone_subgraph(&self, root: IVec2, depth: u32) -> CircuitData {
+        let root_handle = match self.tiles.get(&root).and_then(|tile| tile.component) {
+            Some(handle) => handle,
+            None => return CircuitData::default(),
+        };
+        let mut components = HashSet::from([root_handle]);
+        let mut nets = HashSet::new();
+        let mut frontier = vec![root_handle];
+        for _ in 0..depth {
+            let new_nets: HashSet<u32> = frontier
+                .iter()
+                .flat_map(|handle| {
+                    let (input, output) = self.components[handle].clusters();
+                    input.into_iter().chain(output)
+                })
+                .filter(|net| !nets.contains(net))
+                .collect();
+            if new_nets.is_empty() {
+                break;
+            }
+            frontier = self
+                .components
+                .iter()
+                .filter(|(handle, component)| {
+                    let (input, output) = component.clusters();
+                    !components.contains(handle)
+                        && (input.is_some_and(|net| new_nets.contains(&net))
+                            || output.is_some_and(|net| new_nets.contains(&net)))
+                })
+                .map(|(handle, _)| handle)
+                .collect();
+            components.extend(&frontier);
+            nets.extend(new_nets);
+        }
+
+        let mut components: Vec<ComponentRecord> = components
+            .iter()
+            .map(|handle| self.components[handle].record())
+            .collect();
+        components.sort_by_key(|component| <[i32; 2]>::from(component.position));
+        let mut wires: Vec<WireRecord> = self
+            .wires
+            .iter()
+            .filter(|(_, wire)| nets.contains(&wire.cluster_index))
+            .map(|(_, wire)| WireRecord {
+                start: wire.start,
+                end: wire.end,
+            })
+            .collect();
+        wires.sort_by_key(|wire| (<[i32; 2]>::from(wire.start), <[i32; 2]>::from(wire.end)));
+        CircuitData {
+            components,
+            wires,
+            ..Default::default()
+        }
+        .offset(-root)
+    }
+
+    /// Starts recording the changes made to components, wires and boards, until
+    /// [`Self::take_journal`]. Returns false if already recording.
+    pub fn start_journal(&mut self) -> bool {
+        if self.journal.is_some() {
+            return false;
+        }
+        self.journal = Some(Vec::new());
+        true
+    }
+
+    /// Stops recording and returns the changes made since [`Self::start_journal`] as an undoable
+    /// edit, or `None` if nothing changed.
+    pub fn take_journal(&mut self) -> Option<CircuitEdit> {
+        let changes = self.journal.take()?;
+        if changes.is_empty() {
+            return None;
+        }
+        Some(CircuitEdit::new(changes))
+    }
+
+    fn log(&mut self, change: Change) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(change);
+        }
+    }
+
+    /// The names of the given input and output clusters, see [`Component::clusters`].
+    fn cluster_names(&self, (input, output): (Option<u32>, Option<u32>)) -> [Option<String>; 2] {
+        [input, output].map(|net| net.and_then(|net| self.net_names.get(&net)).cloned())
+    }
+
+    /// Adds the contents of `data` to the circuit, offset by `origin`.
+    ///
+    /// Components and wires that would overlap existing ones are skipped.
+    pub fn import_merge(&mut self, data: &CircuitData, origin: IVec2) {
+        self.insert_data(&data.offset(origin));
+    }
+
+    fn insert_data(&mut self, data: &CircuitData) {
+        for component in &data.components {
+            let position = component.position;
+            if !self.place_component(component.ty, position, component.orientation) {
+                continue;
+            }
+            if component.switched {
+                self.interact(position);
+            }
+            if component.ty == ComponentType::Pin {
+                self.set_pin_kind(position, component.pin_kind);
+            }
+        }
+        for wire in &data.wires {
+            self.place_wire(wire.start, wire.end);
+        }
+    }
+
+    /// Removes every component and wire.
+    pub fn clear(&mut self) {
+        let wires: Vec<depot::Handle> = self.wires.iter().map(|(handle, _)| handle).collect();
+        let components: Vec<depot::Handle> =
+            self.components.iter().map(|(handle, _)| handle).collect();
+
+        // Tear down wires first, so components have no neighbors left and free their clusters.
+        for handle in wires {
+            self.remove_wire(handle);
+        }
+        for handle in components {
+            if let ComponentData::Switch(state, _sprite) = &self.components[&handle].data {
+                if state.switched {
+                    self.simulation.unpower(state.input_cluster_index);
+                }
+            }
+            self.remove_component(handle);
+        }
+        self.tiles.clear();
+    }
+
+    pub fn stats(&self) -> CircuitStats {
+        let mut stats = CircuitStats {
+            wires: self.wires.len(),
+            ..Default::default()
+        };
+        for (_, component) in self.components.iter() {
+            match component.get_type() {
+                ComponentType::Pin => stats.pins += 1,
+                ComponentType::Flip => stats.flips += 1,
+                ComponentType::Flop => stats.flops += 1,
+                ComponentType::Switch => stats.switches += 1,
+            }
+        }
+        stats
     }
 
     pub fn tile_debug_info(&self, pos: IVec2) -> TileDebugInfo {
         TileDebugInfo { circuit: self, pos }
     }
 
+    pub fn validator(&self) -> CircuitValidator<'_> {
+        CircuitValidator { circuit: self }
+    }
+
     pub fn tile(&self, pos: IVec2) -> Option<&Tile> {
         self.tiles.get(&pos)
     }
 
+    /// The component occupying the given tile, with its orientation and state.
+    ///
+    /// Components cover a single tile, which the tile map indexes directly.
+    pub fn gate_at(&self, position: IVec2) -> Option<ComponentRecord> {
+        self.component(position).map(Component::record)
+    }
+
     pub fn component_at(&self, pos: IVec2) -> Option<ComponentType> {
         self.component(pos).map(|component| component.get_type())
     }
 
+    /// The component under the given window coordinates, in pixels.
+    pub fn component_at_screen(
+        &self,
+        screen_position: Vec2,
+        viewport: &Viewport,
+    ) -> Option<ComponentType> {
+        let world_position = viewport.screen_to_world(screen_position);
+        let position = world_position.floor().as_ivec2();
+        if let Some(ty) = self.component_at(position) {
+            return Some(ty);
+        }
+        // Accept clicks just outside the body of a neighboring component, by the same number of
+        // pixels at any zoom.
+        let margin = HIT_MARGIN / viewport.camera().zoom;
+        (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| position + IVec2::new(x, y)))
+            .filter(|&neighbor| {
+                rect::Rect::from(rect::Body { position: neighbor })
+                    .grow(margin)
+                    .contains(world_position)
+            })
+            .find_map(|neighbor| self.component_at(neighbor))
+    }
+
+    /// The net passing through the given tile, if any.
+    ///
+    /// For pins, this is the pin's own net. For other tiles, it is the net of the first wire
+    /// found on the tile.
+    pub fn net_at(&self, position: IVec2) -> Option<NetId> {
+        let tile = self.tile(position)?;
+        if let Some(component) = tile.component.map(|id| self.components.get(&id)) {
+            if let ComponentData::Pin(state, _sprite) = &component.data {
+                return Some(state.cluster_index);
+            }
+        }
+        tile.wires
+            .as_array()
+            .iter()
+            .flatten()
+            .next()
+            .map(|id| self.wires.get(id).cluster_index)
+    }
+
+    /// The user-assigned name of the net passing through the given tile, if any.
+    pub fn net_name(&self, position: IVec2) -> Option<&str> {
+        self.net_at(position)
+            .and_then(|net| self.net_names.get(&net))
+            .map(String::as_str)
+    }
+
+    /// Assigns a name to the net passing through the given tile.
+    ///
+    /// Returns false if there is no net at that position.
+    pub fn set_net_name(&mut self, position: IVec2, name: &str) -> bool {
+        match self.net_at(position) {
+            Some(net) => {
+                self.net_names.insert(net, name.to_owned());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The position of every switch, the circuit's only power source, and whether it is on.
+    pub fn power_sources(&self) -> impl Iterator<Item = (IVec2, bool)> + '_ {
+        self.components
+            .iter()
+            .filter_map(|(_, component)| match &component.data {
+                ComponentData::Switch(state, _sprite) => Some((component.position, state.switched)),
+                _ => None,
+            })
+    }
+
+    /// Forces the nets of the pins at `positions` to `powered`, then runs a single simulation
+    /// tick so the components they feed see the new state.
+    ///
+    /// The forced state is not sticky: later ticks recompute every net from its drivers. Returns
+    /// the positions that had no pin and were skipped.
+    pub fn bulk_set_powered(&mut self, positions: &[IVec2], powered: bool) -> Vec<IVec2> {
+        let mut skipped = Vec::new();
+        for &position in positions {
+            match self.component(position).map(|component| &component.data) {
+                Some(ComponentData::Pin(state, _sprite)) => {
+                    self.simulation.set_powered(state.cluster_index, powered);
+                }
+                _ => skipped.push(position),
+            }
+        }
+        self.simulation.tick();
+        self.rect_renderer.update_cluster_states(&self.simulation);
+        skipped
+    }
+
+    /// Drives the nets of the pins at `positions` from the bits of `pattern` for `ticks` ticks,
+    /// then releases them, see [`Simulation::inject_bit_pattern`].
+    ///
+    /// Returns the positions that had no pin and were skipped; their bits are not driven.
+    pub fn inject_bit_pattern(
+        &mut self,
+        positions: &[IVec2],
+        pattern: u32,
+        ticks: usize,
+    ) -> Vec<IVec2> {
+        let mut skipped = Vec::new();
+        let mut clusters = Vec::new();
+        // The bits of the skipped positions are dropped, so that each pin keeps its own bit.
+        let mut packed = 0;
+        for (bit, &position) in positions.iter().enumerate() {
+            match self.component(position).map(|component| &component.data) {
+                Some(ComponentData::Pin(state, _sprite)) => {
+                    if pattern.checked_shr(bit as u32).unwrap_or(0) & 1 != 0 {
+                        packed |= 1 << clusters.len();
+                    }
+                    clusters.push(state.cluster_index);
+                }
+                _ => skipped.push(position),
+            }
+        }
+        self.simulation.inject_bit_pattern(&clusters, packed, ticks);
+        self.rect_renderer.update_cluster_states(&self.simulation);
+        skipped
+    }
+
+    /// Settles a purely combinational circuit in a single pass over its nets in topological
+    /// order, instead of ticking until it stops changing.
+    ///
+    /// The circuit must not contain feedback loops; this is asserted in debug builds. Release
+    /// builds return false, without changing anything, if there is one.
+    pub fn simulate_combinational_only(&mut self) -> bool {
+        debug_assert!(
+            self.find_feedback_cycles().is_empty(),
+            "simulate_combinational_only on a circuit with feedback loops"
+        );
+        if !self.simulation.settle_acyclic() {
+            return false;
+        }
+        self.rect_renderer.update_cluster_states(&self.simulation);
+        true
+    }
+
+    pub fn pin_kind(&self, position: IVec2) -> Option<PinKind> {
+        match &self.component(position)?.data {
+            ComponentData::Pin(state, _sprite) => Some(state.kind),
+            _ => None,
+        }
+    }
+
+    /// Changes the kind of the pin at the given position.
+    ///
+    /// Returns false if there is no pin there.
+    pub fn set_pin_kind(&mut self, position: IVec2, kind: PinKind) -> bool {
+        let handle = match self.tile(position).and_then(|tile| tile.component) {
+            Some(handle) => handle,
+            None => return false,
+        };
+        match &mut self.components.get_mut(&handle).data {
+            ComponentData::Pin(state, _sprite) => {
+                let from = std::mem::replace(&mut state.kind, kind);
+                self.dirty = true;
+                self.log(Change::SetPinKind {
+                    position,
+                    from,
+                    to: kind,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Output pins whose net is not driven by any component output.
+    ///
+    /// Such pins can never be powered, which is usually a wiring mistake.
+    pub fn undriven_output_pins(&self) -> Vec<IVec2> {
+        self.pins_by_driver_count(PinKind::Output, |drivers| drivers == 0)
+    }
+
+    /// The input pins of the circuit's interface: pins of kind [`PinKind::Input`] whose net no
+    /// component drives, sorted by position.
+    pub fn input_pins(&self) -> Vec<IVec2> {
+        self.pins_by_driver_count(PinKind::Input, |drivers| drivers == 0)
+    }
+
+    /// The output pins of the circuit's interface: pins of kind [`PinKind::Output`] whose net is
+    /// driven by exactly one component output, sorted by position.
+    pub fn output_pins(&self) -> Vec<IVec2> {
+        self.pins_by_driver_count(PinKind::Output, |drivers| drivers == 1)
+    }
+
+    /// The positions of the pins of `kind` for whose net `accept` holds, given the number of
+    /// component outputs driving it. Sorted by position.
+    fn pins_by_driver_count<F>(&self, kind: PinKind, accept: F) -> Vec<IVec2>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let mut drivers: HashMap<NetId, usize> = HashMap::new();
+        for (_, component) in self.components.iter() {
+            if component.get_type() == ComponentType::Pin {
+                continue;
+            }
+            if let (_, Some(output)) = component.clusters() {
+                *drivers.entry(output).or_default() += 1;
+            }
+        }
+        let mut pins: Vec<IVec2> = self
+            .components
+            .iter()
+            .filter_map(|(_, component)| match &component.data {
+                ComponentData::Pin(state, _sprite)
+                    if state.kind == kind
+                        && accept(drivers.get(&state.cluster_index).copied().unwrap_or(0)) =>
+                {
+                    Some(component.position)
+                }
+                _ => None,
+            })
+            .collect();
+        pins.sort_by_key(|&position| <[i32; 2]>::from(position));
+        pins
+    }
+
+    /// Checks the circuit for likely wiring mistakes, sorted by position.
+    ///
+    /// Flags switches sharing their net with another output, feedback loops (which may
+    /// oscillate) and output pins that nothing drives. Other outputs driving the same net are
+    /// a wired-OR and not flagged.
+    pub fn validate(&self) -> Vec<(IVec2, DebugMarkerKind)> {
+        let mut markers = Vec::new();
+
+        let mut drivers: HashMap<NetId, Vec<(IVec2, ComponentType)>> = HashMap::new();
+        for (_, component) in self.components.iter() {
+            if component.get_type() == ComponentType::Pin {
+                continue;
+            }
+            if let (_, Some(output)) = component.clusters() {
+                drivers
+                    .entry(output)
+                    .or_default()
+                    .push((component.position, component.get_type()));
+            }
+        }
+        for driven in drivers.values().filter(|driven| {
+            driven.len() > 1 && driven.iter().any(|&(_, ty)| ty == ComponentType::Switch)
+        }) {
+            markers.extend(
+                driven
+                    .iter()
+                    .map(|&(position, _)| (position, DebugMarkerKind::ShortCircuit)),
+            );
+        }
+
+        if let Err(err) = self.topological_sort() {
+            markers.extend(err.participants.iter().map(|handle| {
+                (
+                    self.components.get(handle).position,
+                    DebugMarkerKind::Oscillation,
+                )
+            }));
+        }
+
+        markers.extend(
+            self.undriven_output_pins()
+                .into_iter()
+                .map(|position| (position, DebugMarkerKind::UndrivenOutput)),
+        );
+
+        markers.extend(
+            self.validator()
+                .check_floating_inputs()
+                .into_iter()
+                .map(|(position, _ty, _input)| (position, DebugMarkerKind::FloatingInput)),
+        );
+
+        markers.sort_by_key(|&(position, kind)| (<[i32; 2]>::from(position), kind));
+        markers.dedup();
+        markers
+    }
+
     pub fn interact(&mut self, pos: IVec2) {
         let component = self
             .tile(pos)
@@ -86,12 +696,14 @@ impl Circuit {
             ComponentData::Flop(..) => {}
             ComponentData::Switch(state, _sprite) => {
                 state.switched = !state.switched;
+                self.dirty = true;
                 if state.switched {
                     self.simulation.power(state.input_cluster_index);
                 } else {
                     self.simulation.unpower(state.input_cluster_index);
                 }
                 component.update_sprite();
+                self.log(Change::ToggleSwitch { position: pos });
             }
         }
     }
@@ -169,8 +781,305 @@ impl Circuit {
     }
 
     pub fn place_wire(&mut self, start: IVec2, end: IVec2) -> bool {
+        self.place_wire_segments(start, end).is_some()
+    }
+
+    /// Places `count` parallel wires spanning `direction`, each offset by `stride` from the
+    /// previous one. [`DEFAULT_WIRE_ARRAY_STRIDE`] stacks horizontal wires vertically.
+    ///
+    /// Wires that cannot be placed are skipped. Returns the handles of all wire segments that were
+    /// inserted.
+    pub fn place_wire_array(
+        &mut self,
+        start: IVec2,
+        direction: IVec2,
+        count: usize,
+        stride: IVec2,
+    ) -> Vec<depot::Handle> {
+        let mut handles = Vec::new();
+        let mut wire_start = start;
+        for _ in 0..count {
+            if let Some(segments) = self.place_wire_segments(wire_start, wire_start + direction) {
+                handles.extend(segments);
+            }
+            wire_start += stride;
+        }
+        handles
+    }
+
+    /// Builds a two-level network computing `table` from the pins at `inputs` to the pins at
+    /// `outputs`, one position per table input and output.
+    ///
+    /// Each output is minimized to a sum of products, see [`synthesis::minimize`]. Every input
+    /// feeds a bus and, through a flip, an inverted bus. Each product term is a flip fed through
+    /// flops from the buses of its complemented literals, and the terms of an output drive its net
+    /// together. The network is placed to the right of everything in the circuit and routed to
+    /// the given positions; the routes must not run into existing components or wires, and no two
+    /// positions may share a column. On error the circuit is left unchanged.
+    pub fn import_from_truthtable(
+        &mut self,
+        inputs: &[IVec2],
+        outputs: &[IVec2],
+        table: &TruthTable,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            inputs.len() == table.input_count() && outputs.len() == table.output_count(),
+            "Expected {} input and {} output positions, got {} and {}",
+            table.input_count(),
+            table.output_count(),
+            inputs.len(),
+            outputs.len()
+        );
+        let endpoints: Vec<IVec2> = inputs.iter().chain(outputs).copied().collect();
+        let mut columns: Vec<i32> = endpoints.iter().map(|position| position.x).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        ensure!(
+            columns.len() == endpoints.len(),
+            "Input and output positions must all be in different columns"
+        );
+
+        let terms: Vec<Vec<synthesis::Implicant>> = (0..table.output_count())
+            .map(|output| synthesis::minimize(table.input_count(), &table.minterms(output)))
+            .collect();
+        let term_count: i32 = terms.iter().map(|terms| terms.len() as i32).sum();
+
+        let content_max_x = self
+            .tiles
+            .iter()
+            .filter(|(_, tile)| tile.component.is_some() || tile.wires.count() != 0)
+            .map(|(position, _)| position.x)
+            .chain(columns.last().copied())
+            .max()
+            .unwrap_or(0);
+        let min_y = endpoints.iter().map(|position| position.y).min();
+        let max_y = endpoints.iter().map(|position| position.y).max();
+        let layout = TruthTableLayout {
+            origin: IVec2::new(content_max_x + SYNTHESIS_MARGIN, min_y.unwrap_or(0)),
+            input_count: table.input_count() as i32,
+            term_count,
+            endpoints_max_y: max_y.unwrap_or(0),
+        };
+
+        let input_routes: Vec<[IVec2; 4]> = inputs
+            .iter()
+            .enumerate()
+            .map(|(index, &position)| layout.input_route(index as i32, position))
+            .collect();
+        let output_routes: Vec<[IVec2; 4]> = outputs
+            .iter()
+            .enumerate()
+            .map(|(index, &position)| layout.output_route(index as i32, position))
+            .collect();
+        for route in input_routes.iter().chain(&output_routes) {
+            let blocked = route
+                .windows(2)
+                .flat_map(|segment| wire_tiles(segment[0], segment[1]))
+                .filter(|&position| position != route[3])
+                .find(|&position| {
+                    self.tile(position)
+                        .is_some_and(|tile| tile.component.is_some() || tile.wires.count() != 0)
+                });
+            if let Some(position) = blocked {
+                bail!("Route to {} is blocked at {}", route[3], position);
+            }
+            // A wire already arriving from the route's side would overlap the route.
+            let arrival = wire_direction(route[3], route[2]);
+            if let Some(tile) = self.tile(route[3]) {
+                if tile.wires.get(arrival).is_some() {
+                    bail!("Route to {} overlaps an existing wire", route[3]);
+                }
+            }
+        }
+
+        let backup = self.to_data();
+        let result = self.build_truthtable_network(&layout, &terms, &input_routes, &output_routes);
+        if result.is_err() {
+            self.load_data(&backup);
+        }
+        result
+    }
+
+    fn build_truthtable_network(
+        &mut self,
+        layout: &TruthTableLayout,
+        terms: &[Vec<synthesis::Implicant>],
+        input_routes: &[[IVec2; 4]],
+        output_routes: &[[IVec2; 4]],
+    ) -> anyhow::Result<()> {
+        let place = |circuit: &mut Self, ty, position, orientation| {
+            if circuit.place_component(ty, position, orientation) {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Cannot place {:?} at {}", ty, position))
+            }
+        };
+        for input in 0..layout.input_count {
+            place(
+                self,
+                ComponentType::Flip,
+                IVec2::new(layout.bus_column(input, true), layout.inverter_row()),
+                Direction::North,
+            )?;
+        }
+
+        let mut term_row = 0;
+        let mut term_positions = Vec::new();
+        for (output, terms) in terms.iter().enumerate() {
+            for term in terms {
+                let y = layout.term_row(term_row);
+                term_row += 1;
+                place(
+                    self,
+                    ComponentType::Flip,
+                    IVec2::new(layout.term_column(), y),
+                    Direction::East,
+                )?;
+                let taps: Vec<i32> = term
+                    .literals(layout.input_count as usize)
+                    // The term is the NOR of the complemented literals.
+                    .map(|(input, positive)| layout.bus_column(input as i32, positive))
+                    .collect();
+                for &bus in &taps {
+                    place(
+                        self,
+                        ComponentType::Flop,
+                        IVec2::new(bus + 1, y + 1),
+                        Direction::East,
+                    )?;
+                }
+                term_positions.push((output as i32, y, taps));
+            }
+        }
+
+        let mut wires = Vec::new();
+        for input in 0..layout.input_count {
+            let bus = layout.bus_column(input, false);
+            let inverted_bus = layout.bus_column(input, true);
+            let bottom = layout.inverter_row();
+            wires.push((IVec2::new(bus, bottom), IVec2::new(bus, layout.bus_top())));
+            wires.push((IVec2::new(bus, bottom), IVec2::new(inverted_bus, bottom)));
+            wires.push((
+                IVec2::new(inverted_bus, bottom),
+                IVec2::new(inverted_bus, layout.bus_top()),
+            ));
+        }
+        for (output, y, taps) in &term_positions {
+            let term_column = layout.term_column();
+            if let Some(&first) = taps.iter().min() {
+                wires.push((IVec2::new(first + 2, *y), IVec2::new(term_column, *y)));
+            }
+            wires.push((
+                IVec2::new(term_column, *y),
+                IVec2::new(layout.output_column(*output), *y),
+            ));
+            for &bus in taps {
+                wires.push((IVec2::new(bus, y + 1), IVec2::new(bus + 1, y + 1)));
+                wires.push((IVec2::new(bus + 1, y + 1), IVec2::new(bus + 2, y + 1)));
+                wires.push((IVec2::new(bus + 2, y + 1), IVec2::new(bus + 2, *y)));
+            }
+        }
+        for route in input_routes.iter().chain(output_routes) {
+            wires.extend(route.windows(2).map(|segment| (segment[0], segment[1])));
+        }
+
+        for (start, end) in wires {
+            if start != end && !self.place_wire(start, end) {
+                bail!("Cannot place wire from {} to {}", start, end);
+            }
+        }
+        Ok(())
+    }
+
+    /// Places `count` ladder rungs starting at `start`, spaced [`RESISTOR_LADDER_SPACING`] tiles
+    /// apart along `orientation`.
+    ///
+    /// Each rung is two parallel wires of length [`RESISTOR_LADDER_RUNG_LENGTH`], perpendicular to
+    /// `orientation`, joined at their far end by a cross-wire. Returns the handles of the two
+    /// parallel wires and the cross-wire of every rung that could be placed completely; if a wire
+    /// was split by existing components, the handle of its first segment is returned.
+    pub fn place_resistor_ladder(
+        &mut self,
+        start: IVec2,
+        count: u8,
+        orientation: Axis,
+    ) -> Vec<(depot::Handle, depot::Handle, depot::Handle)> {
+        let step = orientation.unit();
+        let length = RESISTOR_LADDER_RUNG_LENGTH * orientation.perpendicular().unit();
+        let mut rungs = Vec::new();
+        for index in 0..i32::from(count) {
+            let first = start + index * RESISTOR_LADDER_SPACING * step;
+            let second = first + step;
+            let mut place = |start, end| {
+                self.place_wire_segments(start, end)
+                    .and_then(|segments| segments.first().copied())
+            };
+            let rung = (
+                place(first, first + length),
+                place(second, second + length),
+                place(first + length, second + length),
+            );
+            if let (Some(a), Some(b), Some(cross)) = rung {
+                rungs.push((a, b, cross));
+            }
+        }
+        rungs
+    }
+
+    /// Builds a gate of the given kind out of flips and flops, with its layout's origin at
+    /// `origin`, over a board colored after the kind. See [`gate_layout::GateLayout`].
+    ///
+    /// Returns `None` and leaves the circuit unchanged if any tile of the gate already holds a
+    /// component or a wire. Once placed, the gate is made of ordinary components and wires.
+    pub fn place_gate(&mut self, kind: GateKind, origin: IVec2) -> Option<Gate> {
+        let layout = gate_layout::gate_layout(kind);
+        let occupied = (0..layout.size.x)
+            .flat_map(|x| (0..layout.size.y).map(move |y| origin + IVec2::new(x, y)))
+            .any(|position| {
+                self.tile(position)
+                    .is_some_and(|tile| tile.component.is_some() || tile.wires.count() != 0)
+            });
+        if occupied {
+            return None;
+        }
+
+        for &(ty, position) in layout.components {
+            self.place_component(ty, origin + position, Direction::East);
+        }
+        for &(start, end) in layout.wires {
+            self.place_wire(origin + start, origin + end);
+        }
+        self.place_board(&board::Board::new(origin, layout.size).with_color(kind.color()));
+        Some(Gate::new(kind, origin))
+    }
+
+    /// Places every wire of a JSON array, see [`WireRecord::list_from_json`].
+    ///
+    /// Returns, in the order of the array, the handles of the segments each wire was split into,
+    /// or why it was not placed. Fails only if the text is not a JSON array.
+    pub fn place_wire_from_json(
+        &mut self,
+        json: &str,
+    ) -> anyhow::Result<Vec<anyhow::Result<Vec<depot::Handle>>>> {
+        Ok(WireRecord::list_from_json(json)?
+            .into_iter()
+            .map(|wire| {
+                let WireRecord { start, end } = wire?;
+                match self.place_wire_segments(start, end) {
+                    Some(handles) => Ok(handles),
+                    None => bail!("Cannot place a wire from {start} to {end}"),
+                }
+            })
+            .collect())
+    }
+
+    /// Places a wire, returning the handles of the new segments it was split into.
+    fn place_wire_segments(&mut self, start: IVec2, end: IVec2) -> Option<Vec<depot::Handle>> {
+        if start == end || (start.x != end.x && start.y != end.y) {
+            return None;
+        }
         if !self.can_place_wire(start, end) {
-            return false;
+            return None;
         }
 
         self.place_component(ComponentType::Pin, start, Direction::East);
@@ -186,12 +1095,13 @@ impl Circuit {
             })
             .collect();
 
+        let mut handles = Vec::new();
         for v in split_points.windows(2) {
             let sub_start = v[0];
             let sub_end = v[1];
-            self.insert_wire(sub_start, sub_end);
+            handles.extend(self.insert_wire(sub_start, sub_end));
         }
-        true
+        Some(handles)
     }
 
     pub fn can_place_component(
@@ -304,23 +1214,431 @@ impl Circuit {
             }
 
             let wires = tile.wires.clone();
+            let mut removed = Vec::new();
             if let Some(id) = wires.north {
-                self.remove_wire(id);
+                removed.push(self.remove_wire(id));
             }
             if let Some(id) = wires.south {
                 if wires.south != wires.north {
-                    self.remove_wire(id);
+                    removed.push(self.remove_wire(id));
                 }
             }
             if let Some(id) = wires.east {
-                self.remove_wire(id);
+                removed.push(self.remove_wire(id));
             }
             if let Some(id) = wires.west {
                 if wires.west != wires.east {
-                    self.remove_wire(id);
+                    removed.push(self.remove_wire(id));
+                }
+            }
+            // Only the ends of the removed wires are cleaned up, pins placed on their own stay.
+            for wire in removed {
+                self.remove_pin_if_isolated(wire.start);
+                self.remove_pin_if_isolated(wire.end);
+            }
+        }
+    }
+
+    /// The number of wires ending at the pin at `position`: 0 for an isolated pin, 1 at the end of
+    /// a wire, 2 for a pin in line and 3 or 4 at a junction. 0 if there is no pin there.
+    ///
+    /// Wires are split at pins, so every wire on a pin's tile ends at the pin.
+    pub fn pin_degree(&self, position: IVec2) -> u32 {
+        match self.tiles.get(&position) {
+            Some(tile) if self.component_at(position) == Some(ComponentType::Pin) => {
+                tile.wires.count() as u32
+            }
+            _ => 0,
+        }
+    }
+
+    /// Removes every pin that no wire connects to, and returns how many were removed.
+    pub fn remove_isolated_pins(&mut self) -> usize {
+        let pins: Vec<IVec2> = self
+            .components
+            .iter()
+            .filter(|(_, component)| component.get_type() == ComponentType::Pin)
+            .map(|(_, component)| component.position)
+            .collect();
+        pins.into_iter()
+            .filter(|&position| self.remove_pin_if_isolated(position))
+            .count()
+    }
+
+    /// Joins every pair of wires that meet end to end on the same axis, with nothing else on the
+    /// tile, into a single wire. Returns the number of merges.
+    ///
+    /// Such wires always meet at a pin, which is removed the way [`Self::delete_component`]
+    /// removes pins. Pins marked as inputs or outputs are kept.
+    pub fn merge_collinear_wires(&mut self) -> usize {
+        let pins: Vec<IVec2> = self
+            .components
+            .iter()
+            .filter(|(_, component)| match &component.data {
+                ComponentData::Pin(state, _sprite) => state.kind == PinKind::Bidirectional,
+                _ => false,
+            })
+            .map(|(_, component)| component.position)
+            .collect();
+        let mut merges = 0;
+        for position in pins {
+            let collinear = self.pin_degree(position) == 2
+                && self.tiles.get(&position).is_some_and(|tile| {
+                    let wires = &tile.wires;
+                    let vertical = wires.north.is_some() && wires.south.is_some();
+                    let horizontal = wires.east.is_some() && wires.west.is_some();
+                    vertical || horizontal
+                });
+            if collinear {
+                self.delete_component(position);
+                merges += 1;
+            }
+        }
+        merges
+    }
+
+    fn remove_pin_if_isolated(&mut self, position: IVec2) -> bool {
+        if self.component_at(position) != Some(ComponentType::Pin) || self.pin_degree(position) != 0
+        {
+            return false;
+        }
+        match self.tiles.get(&position).and_then(|tile| tile.component) {
+            Some(handle) => {
+                self.remove_component(handle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every tile where two wires cross or meet at a right angle, sorted by position.
+    pub fn junctions(&self) -> Vec<IVec2> {
+        let wires: Vec<&Wire> = self.wires.iter().map(|(_, wire)| wire).collect();
+        let mut junctions: Vec<IVec2> = wires
+            .iter()
+            .enumerate()
+            .flat_map(|(index, a)| wires[index + 1..].iter().filter_map(|b| a.intersects(b)))
+            .collect();
+        junctions.sort_by_key(|&position| <[i32; 2]>::from(position));
+        junctions.dedup();
+        junctions
+    }
+
+    /// The length of the longest wire segment, in tiles. Unusually long wires often indicate a
+    /// routing mistake.
+    pub fn longest_wire(&self) -> Option<i32> {
+        self.wires.iter().map(|(_, wire)| wire.length()).max()
+    }
+
+    /// The number of wires and components that [`Self::flood_fill_delete`] would remove.
+    pub fn net_element_count(&self, start: IVec2) -> usize {
+        self.net_elements(start)
+            .map_or(0, |(wires, components)| wires.len() + components.len())
+    }
+
+    /// Removes the whole net passing through `start`: its wires and pins, and every component
+    /// reading from or driving it.
+    ///
+    /// Returns the number of removed wires and components.
+    pub fn flood_fill_delete(&mut self, start: IVec2) -> usize {
+        let (wires, components) = match self.net_elements(start) {
+            Some(elements) => elements,
+            None => return 0,
+        };
+        for &handle in &wires {
+            self.remove_wire(handle);
+        }
+        for &handle in &components {
+            if let ComponentData::Switch(state, _sprite) = &self.components[&handle].data {
+                if state.switched {
+                    self.simulation.unpower(state.input_cluster_index);
+                }
+            }
+            self.remove_component(handle);
+        }
+        wires.len() + components.len()
+    }
+
+    fn net_elements(&self, start: IVec2) -> Option<(Vec<depot::Handle>, Vec<depot::Handle>)> {
+        let net = self.net_at(start)?;
+        let wires = self
+            .wires
+            .iter()
+            .filter(|(_, wire)| wire.cluster_index == net)
+            .map(|(handle, _)| handle)
+            .collect();
+        let components = self
+            .components
+            .iter()
+            .filter(|(_, component)| match &component.data {
+                ComponentData::Pin(state, _sprite) => state.cluster_index == net,
+                _ => {
+                    let (input, output) = component.clusters();
+                    input == Some(net) || output == Some(net)
+                }
+            })
+            .map(|(handle, _)| handle)
+            .collect();
+        Some((wires, components))
+    }
+
+    /// Mirrors the entire circuit across the vertical line `x = axis_x`.
+    pub fn mirror_horizontal(&mut self, axis_x: i32) {
+        self.transform(
+            |pos| IVec2::new(2 * axis_x - pos.x, pos.y),
+            Direction::mirror_horizontal,
+        );
+    }
+
+    /// Makes boards placed from now on snap like `grid`, see [`GridAligner::align_board`].
+    pub fn set_grid_aligner(&mut self, grid: GridAligner) {
+        self.grid = grid;
+    }
+
+    pub fn place_board(&mut self, board: &board::Board) {
+        let board = self.grid.align_board(board);
+        self.insert_board(self.boards.len(), board);
+    }
+
+    /// Places a background board covering every selected tile, with `padding` tiles to spare on
+    /// each side. Returns the placed board, or `None` if the selection is empty.
+    pub fn place_board_around_selection(
+        &mut self,
+        selection: &Selection,
+        padding: u32,
+    ) -> Option<board::Board> {
+        let bounds = selection.bounds()?;
+        let padding = IVec2::splat(padding.try_into().unwrap_or(i32::MAX));
+        let board = board::Board::new(bounds.min - padding, bounds.max - bounds.min + 2 * padding)
+            .with_color(self.board_color);
+        self.place_board(&board);
+        self.boards.last().map(|(_handle, board)| *board)
+    }
+
+    /// The summed [`board::Board::area`] of every board, excluding the root board. Overlapping
+    /// boards are counted once each.
+    pub fn total_board_area(&self) -> u64 {
+        self.boards
+            .iter()
+            .map(|(_handle, board)| board.area())
+            .sum()
+    }
+
+    /// The topmost board covering the given position, excluding the root board.
+    pub fn board_at(&self, position: IVec2) -> Option<board::Board> {
+        self.board_index_at(position)
+            .map(|index| self.boards[index].1)
+    }
+
+    /// Replaces the topmost board covering the given position with `board`, e.g. to resize or
+    /// recolor it, without changing its draw order.
+    ///
+    /// Returns false if there is no board there.
+    pub fn update_board_at(&mut self, position: IVec2, board: &board::Board) -> bool {
+        match self.board_index_at(position) {
+            Some(index) => {
+                self.set_board(index, *board);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deletes the topmost board covering the given position.
+    ///
+    /// Returns false if there is no board there. The root board cannot be deleted.
+    pub fn delete_board_at(&mut self, position: IVec2) -> bool {
+        match self.board_index_at(position) {
+            Some(index) => {
+                self.remove_board(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deletes everything on the tile at the given position, or the board under it if the tile is
+    /// empty.
+    pub fn delete_at(&mut self, position: IVec2) {
+        let occupied = self
+            .tile(position)
+            .map(|tile| tile.component.is_some() || tile.wires.count() != 0)
+            .unwrap_or(false);
+        if occupied {
+            self.delete_all_at(position);
+        } else {
+            self.delete_board_at(position);
+        }
+    }
+
+    /// Orders the circuit's logic components so that every component comes after the components
+    /// driving its input.
+    ///
+    /// Pins are not included, as they only join wires into nets. If the dependency graph has a
+    /// cycle, the components that are part of (or sit between) cycles are returned in the error.
+    pub fn topological_sort(&self) -> Result<Vec<ComponentId>, CycleError> {
+        let (gates, dependents) = self.dependency_graph();
+        let mut in_degree: HashMap<ComponentId, usize> =
+            gates.iter().map(|&handle| (handle, 0)).collect();
+        for dependent in dependents.values().flatten() {
+            *in_degree.get_mut(dependent).unwrap() += 1;
+        }
+
+        let mut order = Vec::with_capacity(in_degree.len());
+        let mut queue: Vec<ComponentId> = in_degree
+            .iter()
+            .filter(|(_, &degree)| degree == 0)
+            .map(|(&handle, _)| handle)
+            .collect();
+        while let Some(handle) = queue.pop() {
+            order.push(handle);
+            for dependent in dependents.get(&handle).into_iter().flatten() {
+                let degree = in_degree.get_mut(dependent).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push(*dependent);
                 }
             }
         }
+
+        if order.len() == in_degree.len() {
+            return Ok(order);
+        }
+
+        // Everything left over is either on a cycle or downstream of one. Peel off the
+        // downstream components, which have no dependents among the remaining set.
+        let mut remaining: HashSet<ComponentId> = in_degree
+            .into_iter()
+            .filter(|&(_, degree)| degree != 0)
+            .map(|(handle, _)| handle)
+            .collect();
+        loop {
+            let sinks: Vec<ComponentId> = remaining
+                .iter()
+                .filter(|handle| {
+                    !dependents
+                        .get(handle)
+                        .into_iter()
+                        .flatten()
+                        .any(|dependent| remaining.contains(dependent))
+                })
+                .copied()
+                .collect();
+            if sinks.is_empty() {
+                break;
+            }
+            for sink in sinks {
+                remaining.remove(&sink);
+            }
+        }
+        Err(CycleError {
+            participants: remaining.into_iter().collect(),
+        })
+    }
+
+    /// The groups of components that feed each other in a loop, such as oscillators and latches.
+    ///
+    /// Each group is a strongly connected component of the dependency graph used by
+    /// [`Self::topological_sort`], found with Tarjan's algorithm. Single components only form a
+    /// group if they feed themselves.
+    pub fn find_feedback_cycles(&self) -> Vec<Vec<ComponentId>> {
+        let (gates, dependents) = self.dependency_graph();
+        strongly_connected_components(&gates, &dependents)
+            .into_iter()
+            .filter(|group| match group.as_slice() {
+                [single] => dependents
+                    .get(single)
+                    .is_some_and(|next| next.contains(single)),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// The position of a component found by [`Self::topological_sort`] or
+    /// [`Self::find_feedback_cycles`], if it still exists.
+    pub fn component_position(&self, id: ComponentId) -> Option<IVec2> {
+        self.components
+            .iter()
+            .find(|&(handle, _)| handle == id)
+            .map(|(_, component)| component.position)
+    }
+
+    /// The components, other than pins, whose input is on the net driven by `id`'s output. Each
+    /// is listed once, and `id` itself is included if it feeds itself.
+    pub fn connected_gates(&self, id: ComponentId) -> Vec<ComponentId> {
+        let output = match self
+            .components
+            .iter()
+            .find(|&(handle, _)| handle == id)
+            .and_then(|(_, component)| component.clusters().1)
+        {
+            Some(output) => output,
+            None => return Vec::new(),
+        };
+        self.components
+            .iter()
+            .filter(|(_, component)| component.get_type() != ComponentType::Pin)
+            .filter(|(_, component)| component.clusters().0 == Some(output))
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    /// Writes the graph of components other than pins to `path` in Graphviz DOT format.
+    ///
+    /// Each component is a node labeled with its type and position, with an edge to every
+    /// component reading its output. Switches, the power sources, are ranked as roots.
+    pub fn export_dot(&self, path: &Path) -> anyhow::Result<()> {
+        let (gates, dependents) = self.dependency_graph();
+        let indices: HashMap<ComponentId, usize> = gates
+            .iter()
+            .enumerate()
+            .map(|(index, &handle)| (handle, index))
+            .collect();
+        let nodes: Vec<(ComponentType, IVec2)> = gates
+            .iter()
+            .map(|handle| {
+                let component = &self.components[handle];
+                (component.get_type(), component.position)
+            })
+            .collect();
+        let mut edges = Vec::new();
+        for (index, handle) in gates.iter().enumerate() {
+            for dependent in dependents.get(handle).into_iter().flatten() {
+                edges.push((index, indices[dependent]));
+            }
+        }
+
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        write_dot(std::io::BufWriter::new(file), &nodes, &edges)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Every component except pins, and for each the components reading its output, once per
+    /// connection.
+    fn dependency_graph(&self) -> (Vec<ComponentId>, HashMap<ComponentId, Vec<ComponentId>>) {
+        let mut drivers: HashMap<NetId, Vec<ComponentId>> = HashMap::new();
+        let mut gates = Vec::new();
+        for (handle, component) in self.components.iter() {
+            if component.get_type() == ComponentType::Pin {
+                continue;
+            }
+            gates.push(handle);
+            if let (_, Some(output)) = component.clusters() {
+                drivers.entry(output).or_default().push(handle);
+            }
+        }
+
+        // Edges point from a driving component to the components it drives.
+        let mut dependents: HashMap<ComponentId, Vec<ComponentId>> = HashMap::new();
+        for &handle in &gates {
+            if let (Some(input), _) = self.components[&handle].clusters() {
+                for &dep in drivers.get(&input).into_iter().flatten() {
+                    dependents.entry(dep).or_default().push(handle);
+                }
+            }
+        }
+        (gates, dependents)
     }
 
     pub fn wire_connection(&self, position: IVec2, direction: Direction) -> Option<WireConnection> {
@@ -333,13 +1651,13 @@ impl Circuit {
         ty: ComponentType,
         position: IVec2,
         orientation: Direction,
-    ) -> bool {
+    ) -> Option<depot::Handle> {
         if self
             .tile(position)
             .and_then(|tile| tile.component)
             .is_some()
         {
-            return false;
+            return None;
         }
         let data = match ty {
             ComponentType::Pin => {
@@ -368,7 +1686,10 @@ impl Circuit {
                     None => self.simulation.alloc_cluster(),
                 };
 
-                let state = PinState { cluster_index };
+                let state = PinState {
+                    cluster_index,
+                    kind: PinKind::default(),
+                };
                 let sprite = PinSprite {
                     pin: self.rect_renderer.insert(&Default::default()),
                 };
@@ -492,35 +1813,35 @@ impl Circuit {
         };
         component.update_sprite();
 
+        self.dirty = true;
+        let record = component.record();
         let id = self.components.insert(component);
         let tile = self.tiles.entry(position).or_default();
         tile.component = Some(id);
         tile.update_crossover(position, &mut self.rect_renderer);
-        true
+        self.log(Change::AddComponent {
+            record,
+            net_names: Default::default(),
+        });
+        Some(id)
     }
 
-    fn insert_wire(&mut self, start: IVec2, end: IVec2) -> bool {
-        // Lexicographically order the start/end points to ensure "backwards" duplicates
-        // get caught.
-        if <[i32; 2]>::from(start) > <[i32; 2]>::from(end) {
-            return self.insert_wire(end, start);
-        }
-
+    fn insert_wire(&mut self, start: IVec2, end: IVec2) -> Option<depot::Handle> {
         // Either a wire's start and end X coordinates need to be the same,
         // or their Y coordinates need to be the same, but not both.
         // (If both, then the wire would be zero-length, which is not useful.)
         assert!(
-            (start.x == end.x) ^ (start.y == end.y),
+            wire_is_horizontal(start, end) ^ wire_is_vertical(start, end),
             "Illegal wire start and end positions"
         );
-
-        if let Some(tile) = self.tiles.get(&start) {
-            for &id in tile.wires.as_array().iter().flatten() {
-                let wire = &self.wires[&id];
-                if wire.start == start && wire.end == end {
-                    return false;
-                }
-            }
+
+        // Wires are stored normalized, so look for a backwards duplicate too.
+        if self
+            .wire_between(start, end)
+            .or_else(|| self.wire_between(end, start))
+            .is_some()
+        {
+            return None;
         }
 
         let direction = wire_direction(start, end);
@@ -562,15 +1883,23 @@ impl Circuit {
         };
 
         let instance = self.rect_renderer.insert(&Default::default());
-        let id = self.wires.insert(Wire {
-            start,
-            end,
-            start_connection,
-            end_connection,
-            instance,
-            cluster_index,
-        });
+        self.dirty = true;
+        let id = self.wires.insert(
+            Wire {
+                start,
+                end,
+                start_connection,
+                end_connection,
+                instance,
+                cluster_index,
+            }
+            .normalize(),
+        );
         let wire = self.wires.get(&id);
+        let record = WireRecord {
+            start: wire.start,
+            end: wire.end,
+        };
         wire.update_sprite();
         for pos in wire.tiles() {
             let tile = self.tiles.entry(pos).or_default();
@@ -582,18 +1911,32 @@ impl Circuit {
             }
             tile.update_crossover(pos, &mut self.rect_renderer);
         }
-        true
+        self.log(Change::AddWire { record });
+        Some(id)
+    }
+
+    /// The wire going exactly from `start` to `end`, in that order.
+    fn wire_between(&self, start: IVec2, end: IVec2) -> Option<depot::Handle> {
+        let tile = self.tiles.get(&start)?;
+        tile.wires.as_array().iter().flatten().copied().find(|id| {
+            let wire = &self.wires[id];
+            wire.start == start && wire.end == end
+        })
     }
 
     fn remove_component(&mut self, component_id: depot::Handle) -> Component {
         let component = self.components.get(&component_id);
+        let change = Change::RemoveComponent {
+            record: component.record(),
+            net_names: self.cluster_names(component.clusters()),
+        };
 
         // Move/copy out to prevent lifetime errors
         let orientation = component.orientation;
         match &component.data {
             ComponentData::Pin(state, _sprite) => {
                 if !self.has_neighbors(&GraphNode::Component(component_id, Direction::North)) {
-                    self.simulation.free_cluster(state.cluster_index);
+                    self.free_cluster(state.cluster_index);
                 }
             }
             ComponentData::Flip(state, _sprite) => {
@@ -609,10 +1952,10 @@ impl Circuit {
 
                 if !self.has_neighbors(&GraphNode::Component(component_id, orientation.opposite()))
                 {
-                    self.simulation.free_cluster(input_cluster_index);
+                    self.free_cluster(input_cluster_index);
                 }
                 if !self.has_neighbors(&GraphNode::Component(component_id, orientation)) {
-                    self.simulation.free_cluster(output_cluster_index);
+                    self.free_cluster(output_cluster_index);
                 }
             }
             ComponentData::Flop(state, _sprite) => {
@@ -628,10 +1971,10 @@ impl Circuit {
 
                 if !self.has_neighbors(&GraphNode::Component(component_id, orientation.opposite()))
                 {
-                    self.simulation.free_cluster(input_cluster_index);
+                    self.free_cluster(input_cluster_index);
                 }
                 if !self.has_neighbors(&GraphNode::Component(component_id, orientation)) {
-                    self.simulation.free_cluster(output_cluster_index);
+                    self.free_cluster(output_cluster_index);
                 }
             }
             ComponentData::Switch(state, _sprite) => {
@@ -644,14 +1987,15 @@ impl Circuit {
                 self.simulation
                     .remove_flop(input_cluster_index, output_cluster_index);
 
-                self.simulation.free_cluster(input_cluster_index);
+                self.free_cluster(input_cluster_index);
 
                 if !self.has_neighbors(&GraphNode::Component(component_id, orientation)) {
-                    self.simulation.free_cluster(output_cluster_index);
+                    self.free_cluster(output_cluster_index);
                 }
             }
         }
 
+        self.dirty = true;
         let component = self.components.remove(&component_id);
         let tile = self.tiles.get_mut(&component.position).unwrap();
         tile.component = None;
@@ -678,16 +2022,25 @@ impl Circuit {
             ComponentData::Flop(..) => {}
             ComponentData::Switch(..) => {}
         }
+        self.log(change);
         component
     }
 
     fn remove_wire(&mut self, wire_id: depot::Handle) -> Wire {
         let wire = self.wires.get(&wire_id);
+        let change = Change::RemoveWire {
+            record: WireRecord {
+                start: wire.start,
+                end: wire.end,
+            },
+            net_name: self.net_names.get(&wire.cluster_index).cloned(),
+        };
 
         if !self.has_neighbors(&GraphNode::Wire(wire_id)) {
-            self.simulation.free_cluster(wire.cluster_index);
+            self.free_cluster(wire.cluster_index);
         }
 
+        self.dirty = true;
         let wire = self.wires.remove(&wire_id);
         for tile_pos in wire.tiles() {
             let tile = self.tiles.get_mut(&tile_pos).unwrap();
@@ -711,9 +2064,72 @@ impl Circuit {
             );
         }
 
+        self.log(change);
         wire
     }
 
+    /// Rebuilds the circuit with every position and orientation mapped through the given
+    /// functions.
+    ///
+    /// Clusters are re-allocated from scratch, so any transient signal state is lost; switches
+    /// keep their switched state.
+    fn transform<P, O>(&mut self, position: P, orientation: O)
+    where
+        P: Fn(IVec2) -> IVec2,
+        O: Fn(Direction) -> Direction,
+    {
+        let mut data = self.to_data();
+        for component in &mut data.components {
+            component.position = position(component.position);
+            component.orientation = orientation(component.orientation);
+        }
+        for wire in &mut data.wires {
+            wire.start = position(wire.start);
+            wire.end = position(wire.end);
+        }
+        self.load_data(&data);
+    }
+
+    fn insert_board(&mut self, index: usize, board: board::Board) {
+        let handle = self.board_renderer.insert(&board);
+        self.boards.insert(index, (handle, board));
+        self.dirty = true;
+        self.log(Change::AddBoard { index, board });
+    }
+
+    fn set_board(&mut self, index: usize, board: board::Board) {
+        let (handle, old_board) = &mut self.boards[index];
+        self.board_renderer.update(handle, &board);
+        let from = std::mem::replace(old_board, board);
+        self.dirty = true;
+        self.log(Change::UpdateBoard {
+            index,
+            from,
+            to: board,
+        });
+    }
+
+    fn remove_board(&mut self, index: usize) {
+        let (handle, board) = self.boards.remove(index);
+        self.board_renderer.remove(handle);
+        self.dirty = true;
+        self.log(Change::RemoveBoard { index, board });
+    }
+
+    fn board_index_at(&self, position: IVec2) -> Option<usize> {
+        self.boards
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, board))| board.contains(position))
+            .max_by_key(|(_, (_, board))| board.z_index)
+            .map(|(index, _)| index)
+    }
+
+    fn free_cluster(&mut self, id: u32) {
+        self.net_names.remove(&id);
+        self.simulation.free_cluster(id);
+    }
+
     fn component(&self, position: IVec2) -> Option<&Component> {
         self.tile(position)
             .and_then(|tile| tile.component)
@@ -808,7 +2224,11 @@ impl Circuit {
             into_index,
             self.simulation.is_powered(into_index) || self.simulation.is_powered(from_index),
         );
-        self.simulation.free_cluster(from_index);
+        // Keep the name of the merged net if the surviving net does not have one.
+        if let Some(name) = self.net_names.remove(&from_index) {
+            self.net_names.entry(into_index).or_insert(name);
+        }
+        self.free_cluster(from_index);
     }
 
     fn split_clusters(&mut self, keep: GraphNode, split: GraphNode) {
@@ -1037,6 +2457,73 @@ impl Circuit {
     }
 }
 
+/// Counts of the parts that make up a circuit.
+///
+/// `Display` formats the counts as a Markdown table; `Debug` only shows the totals.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub pins: usize,
+    pub flips: usize,
+    pub flops: usize,
+    pub switches: usize,
+    pub wires: usize,
+}
+
+impl CircuitStats {
+    pub fn components(&self) -> usize {
+        self.pins + self.flips + self.flops + self.switches
+    }
+
+    pub fn total(&self) -> usize {
+        self.components() + self.wires
+    }
+
+    pub fn print_report(&self) {
+        print!("{}", self);
+    }
+
+    fn rows(&self) -> [(&'static str, usize); 5] {
+        [
+            ("Pin", self.pins),
+            ("Flip", self.flips),
+            ("Flop", self.flops),
+            ("Switch", self.switches),
+            ("Wire", self.wires),
+        ]
+    }
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total();
+        let percent = |count: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                100.0 * count as f32 / total as f32
+            }
+        };
+        writeln!(f, "| Type | Count | Percent |")?;
+        writeln!(f, "|------|------:|--------:|")?;
+        for (name, count) in self.rows() {
+            writeln!(f, "| {} | {} | {:.1}% |", name, count, percent(count))?;
+        }
+        writeln!(f, "| **Total** | {} | {:.1}% |", total, percent(total))
+    }
+}
+
+impl fmt::Debug for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CircuitStats(total: {}, components: {}, wires: {})",
+            self.total(),
+            self.components(),
+            self.wires
+        )
+    }
+}
+
 pub struct TileDebugInfo<'a> {
     circuit: &'a Circuit,
     pos: IVec2,
@@ -1045,11 +2532,18 @@ pub struct TileDebugInfo<'a> {
 impl<'a> fmt::Display for TileDebugInfo<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(tile) = self.circuit.tile(self.pos) {
+            if let Some(name) = self.circuit.net_name(self.pos) {
+                writeln!(f, "Net: {}", name)?;
+            }
             if let Some(component_handle) = tile.component {
                 let component = self.circuit.components.get(&component_handle);
                 match &component.data {
                     ComponentData::Pin(state, _sprite) => {
-                        writeln!(f, "Component: Pin ({})", state.cluster_index)?;
+                        writeln!(
+                            f,
+                            "Component: Pin ({}, {:?})",
+                            state.cluster_index, state.kind,
+                        )?;
                     }
                     ComponentData::Flip(state, _sprite) => {
                         writeln!(
@@ -1159,7 +2653,7 @@ impl TileWires {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ComponentType {
     Pin,
     Flip,
@@ -1183,6 +2677,54 @@ impl Component {
         }
     }
 
+    fn record(&self) -> ComponentRecord {
+        ComponentRecord {
+            ty: self.get_type(),
+            position: self.position,
+            orientation: self.orientation,
+            switched: match &self.data {
+                ComponentData::Switch(state, _sprite) => state.switched,
+                _ => false,
+            },
+            pin_kind: match &self.data {
+                ComponentData::Pin(state, _sprite) => state.kind,
+                _ => PinKind::default(),
+            },
+        }
+    }
+
+    /// The input and output clusters of the component, if it has them.
+    ///
+    /// Pins report their single cluster as input and/or output depending on their kind.
+    fn clusters(&self) -> (Option<u32>, Option<u32>) {
+        match &self.data {
+            ComponentData::Pin(state, _sprite) => match state.kind {
+                PinKind::Input => (Some(state.cluster_index), None),
+                PinKind::Output => (None, Some(state.cluster_index)),
+                PinKind::Bidirectional => (Some(state.cluster_index), Some(state.cluster_index)),
+            },
+            ComponentData::Flip(state, _sprite) => (
+                Some(state.input_cluster_index),
+                Some(state.output_cluster_index),
+            ),
+            ComponentData::Flop(state, _sprite) => (
+                Some(state.input_cluster_index),
+                Some(state.output_cluster_index),
+            ),
+            // The input cluster of a switch is private and only powered by interacting with it.
+            ComponentData::Switch(state, _sprite) => (None, Some(state.output_cluster_index)),
+        }
+    }
+
+    /// The sides of the component that wires can feed its input from.
+    fn input_directions(&self) -> Vec<Direction> {
+        gate_layout::pin_directions(self.get_type(), self.orientation)
+            .into_iter()
+            .filter(|&(kind, _)| kind == PinKind::Input)
+            .map(|(_, direction)| direction)
+            .collect()
+    }
+
     fn connection_type(&self, direction: Direction) -> WireConnection {
         match self.get_type() {
             ComponentType::Pin => WireConnection::Pin,
@@ -1334,6 +2876,7 @@ enum ComponentData {
 
 struct PinState {
     cluster_index: u32,
+    kind: PinKind,
 }
 
 struct PinSprite {
@@ -1384,10 +2927,64 @@ struct Wire {
 }
 
 impl Wire {
+    /// The same wire with its endpoints ordered lexicographically, by X then Y, so that a wire
+    /// and its reverse are stored the same way.
+    fn normalize(self) -> Self {
+        if <[i32; 2]>::from(self.start) > <[i32; 2]>::from(self.end) {
+            Self {
+                start: self.end,
+                end: self.start,
+                start_connection: self.end_connection,
+                end_connection: self.start_connection,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+
     fn tiles(&self) -> impl Iterator<Item = IVec2> {
         wire_tiles(self.start, self.end)
     }
 
+    /// The length of the wire in tiles. Wires are axis-aligned, so this is also the Manhattan
+    /// length.
+    fn length(&self) -> i32 {
+        (self.end - self.start).abs().max_element()
+    }
+
+    fn is_horizontal(&self) -> bool {
+        wire_is_horizontal(self.start, self.end)
+    }
+
+    fn is_vertical(&self) -> bool {
+        wire_is_vertical(self.start, self.end)
+    }
+
+    /// The tile shared by this wire and `other` if one is horizontal and the other vertical,
+    /// including their endpoints.
+    ///
+    /// Returns `None` for parallel wires, even if they overlap.
+    fn intersects(&self, other: &Wire) -> Option<IVec2> {
+        let (horizontal, vertical) = if self.is_horizontal() && other.is_vertical() {
+            (self, other)
+        } else if self.is_vertical() && other.is_horizontal() {
+            (other, self)
+        } else {
+            return None;
+        };
+        let point = IVec2::new(vertical.start.x, horizontal.start.y);
+        let within =
+            |value: i32, from: i32, to: i32| from.min(to) <= value && value <= from.max(to);
+        if within(point.x, horizontal.start.x, horizontal.end.x)
+            && within(point.y, vertical.start.y, vertical.end.y)
+        {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
     fn direction(&self) -> Direction {
         wire_direction(self.start, self.end)
     }
@@ -1410,14 +3007,250 @@ impl Wire {
     }
 }
 
+impl EditTarget for Circuit {
+    fn restore_component(&mut self, record: &ComponentRecord, net_names: &[Option<String>; 2]) {
+        let handle = match self.insert_component(record.ty, record.position, record.orientation) {
+            Some(handle) => handle,
+            None => return,
+        };
+        if record.switched {
+            self.interact(record.position);
+        }
+        if record.ty == ComponentType::Pin {
+            self.set_pin_kind(record.position, record.pin_kind);
+        }
+        let (input, output) = self.components[&handle].clusters();
+        for (net, name) in [input, output].into_iter().zip(net_names) {
+            if let (Some(net), Some(name)) = (net, name) {
+                self.net_names.entry(net).or_insert_with(|| name.clone());
+            }
+        }
+    }
+
+    fn remove_component_at(&mut self, position: IVec2) {
+        let handle = match self.tile(position).and_then(|tile| tile.component) {
+            Some(handle) => handle,
+            None => return,
+        };
+        // Switches may have been toggled since, outside of any edit.
+        if let ComponentData::Switch(state, _sprite) = &self.components[&handle].data {
+            if state.switched {
+                self.interact(position);
+            }
+        }
+        self.remove_component(handle);
+    }
+
+    fn restore_wire(&mut self, record: WireRecord, net_name: Option<&str>) {
+        if let Some(handle) = self.insert_wire(record.start, record.end) {
+            if let Some(name) = net_name {
+                let net = self.wires[&handle].cluster_index;
+                self.net_names.entry(net).or_insert_with(|| name.to_owned());
+            }
+        }
+    }
+
+    fn remove_wire_between(&mut self, record: WireRecord) {
+        if let Some(handle) = self.wire_between(record.start, record.end) {
+            self.remove_wire(handle);
+        }
+    }
+
+    fn set_pin_kind_at(&mut self, position: IVec2, kind: PinKind) {
+        self.set_pin_kind(position, kind);
+    }
+
+    fn toggle_switch_at(&mut self, position: IVec2) {
+        self.interact(position);
+    }
+
+    fn insert_board_at(&mut self, index: usize, board: board::Board) {
+        self.insert_board(index, board);
+    }
+
+    fn remove_board_at(&mut self, index: usize) {
+        self.remove_board(index);
+    }
+
+    fn set_board_at(&mut self, index: usize, board: board::Board) {
+        self.set_board(index, board);
+    }
+
+    /// Applies the changes without recording them. Everything they do not touch keeps its
+    /// clusters, their state and their names.
+    fn apply_changes(&mut self, changes: &[Change], undo: bool) {
+        let journal = self.journal.take();
+        journal::apply_changes(self, changes, undo);
+        self.journal = journal;
+    }
+}
+
+/// Identifies a component placed in a circuit.
+pub type ComponentId = depot::Handle;
+
+/// A problem found by [`Circuit::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugMarkerKind {
+    /// A switch and another output drive the same net, so the switch cannot turn it off.
+    ShortCircuit,
+    /// The component is part of a feedback loop.
+    Oscillation,
+    /// An output pin on a net that nothing drives.
+    UndrivenOutput,
+    /// A component input with no wire connected to it.
+    FloatingInput,
+}
+
+/// Design rule checks over a circuit.
+pub struct CircuitValidator<'a> {
+    circuit: &'a Circuit,
+}
+
+impl<'a> CircuitValidator<'a> {
+    /// Component inputs that no wire is connected to, as `(position, type, input index)`,
+    /// sorted by position.
+    ///
+    /// All sides of a flip feed the same input, so it only counts as floating if none of them
+    /// has a wire.
+    pub fn check_floating_inputs(&self) -> Vec<(IVec2, ComponentType, u8)> {
+        let mut floating: Vec<(IVec2, ComponentType, u8)> = self
+            .circuit
+            .components
+            .iter()
+            .filter(|(_, component)| {
+                let inputs = component.input_directions();
+                let tile = self.circuit.tile(component.position);
+                !inputs.is_empty()
+                    && !inputs
+                        .iter()
+                        .any(|&dir| tile.and_then(|tile| tile.wires.get(dir)).is_some())
+            })
+            .map(|(_, component)| (component.position, component.get_type(), 0))
+            .collect();
+        floating.sort_by_key(|&(position, ..)| <[i32; 2]>::from(position));
+        floating
+    }
+}
+
+/// Returned by [`Circuit::topological_sort`] when components depend on each other's outputs.
+#[derive(Debug, Clone)]
+pub struct CycleError {
+    pub participants: Vec<ComponentId>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "circuit contains a feedback cycle through {} components",
+            self.participants.len()
+        )
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Identifies an electrically-connected net. This is the simulation cluster index of the net.
+pub type NetId = u32;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum GraphNode {
     Wire(depot::Handle),
     Component(depot::Handle, Direction),
 }
 
+/// How far outside of a component body clicks still hit it, in pixels.
+const HIT_MARGIN: f32 = 6.0;
+
+/// The dash pattern of wires in the clock net display, in tiles.
+const CLOCK_NET_DASH_LENGTH: f32 = 0.5;
+const CLOCK_NET_GAP_LENGTH: f32 = 0.25;
+
+/// The stride that stacks wires placed with [`Circuit::place_wire_array`] vertically.
+pub const DEFAULT_WIRE_ARRAY_STRIDE: IVec2 = IVec2::Y;
+
+/// Horizontal gap between existing content and a network built by
+/// [`Circuit::import_from_truthtable`].
+const SYNTHESIS_MARGIN: i32 = 3;
+
+/// Tile positions used by [`Circuit::import_from_truthtable`].
+///
+/// From left to right: a plain and an inverted bus per input, three columns apart so taps fit in
+/// between; a column of term flips; one column per output. Terms take two rows each above
+/// `origin`, the inverters sit two rows below it, and each route gets its own row below the
+/// inverters (inputs) or above everything (outputs).
+struct TruthTableLayout {
+    origin: IVec2,
+    input_count: i32,
+    term_count: i32,
+    endpoints_max_y: i32,
+}
+
+impl TruthTableLayout {
+    fn bus_column(&self, input: i32, inverted: bool) -> i32 {
+        self.origin.x + 3 * (2 * input + inverted as i32)
+    }
+
+    fn term_column(&self) -> i32 {
+        self.origin.x + 6 * self.input_count + 1
+    }
+
+    fn output_column(&self, output: i32) -> i32 {
+        self.term_column() + 2 + 2 * output
+    }
+
+    fn inverter_row(&self) -> i32 {
+        self.origin.y - 2
+    }
+
+    fn term_row(&self, term: i32) -> i32 {
+        self.origin.y + 2 * term
+    }
+
+    fn bus_top(&self) -> i32 {
+        (self.origin.y + 2 * self.term_count - 1).max(self.origin.y - 1)
+    }
+
+    fn output_row(&self, output: i32) -> i32 {
+        self.bus_top().max(self.endpoints_max_y) + 2 + 2 * output
+    }
+
+    /// From the bottom of the input's bus, down to its route row, across, and up to `position`.
+    fn input_route(&self, input: i32, position: IVec2) -> [IVec2; 4] {
+        let column = self.bus_column(input, false);
+        let row = self.inverter_row() - 2 - 2 * input;
+        [
+            IVec2::new(column, self.inverter_row()),
+            IVec2::new(column, row),
+            IVec2::new(position.x, row),
+            position,
+        ]
+    }
+
+    /// Up the output's column, across, and down to `position`.
+    fn output_route(&self, output: i32, position: IVec2) -> [IVec2; 4] {
+        let column = self.output_column(output);
+        let row = self.output_row(output);
+        [
+            IVec2::new(column, self.origin.y),
+            IVec2::new(column, row),
+            IVec2::new(position.x, row),
+            position,
+        ]
+    }
+}
+
+/// Distance between consecutive rungs of [`Circuit::place_resistor_ladder`].
+pub const RESISTOR_LADDER_SPACING: i32 = 3;
+/// Length of the parallel wires of a [`Circuit::place_resistor_ladder`] rung.
+pub const RESISTOR_LADDER_RUNG_LENGTH: i32 = 4;
+
+fn make_root_board(color: [f32; 4]) -> board::Board {
+    board::Board::new(IVec2::new(-10_000, -10_000), IVec2::new(20_000, 20_000)).with_color(color)
+}
+
 pub fn wire_direction(start: IVec2, end: IVec2) -> Direction {
-    if start.x == end.x {
+    if wire_is_vertical(start, end) {
         if start.y < end.y {
             Direction::North
         } else {
@@ -1432,6 +3265,28 @@ pub fn wire_direction(start: IVec2, end: IVec2) -> Direction {
     }
 }
 
+/// Whether a wire from `start` to `end` runs along the X axis. Wires are never diagonal; this is
+/// checked in debug builds.
+fn wire_is_horizontal(start: IVec2, end: IVec2) -> bool {
+    debug_assert_axis_aligned(start, end);
+    start.y == end.y
+}
+
+/// Whether a wire from `start` to `end` runs along the Y axis, see [`wire_is_horizontal`].
+fn wire_is_vertical(start: IVec2, end: IVec2) -> bool {
+    debug_assert_axis_aligned(start, end);
+    start.x == end.x
+}
+
+fn debug_assert_axis_aligned(start: IVec2, end: IVec2) {
+    debug_assert!(
+        start.x == end.x || start.y == end.y,
+        "Diagonal wire from {} to {}",
+        start,
+        end
+    );
+}
+
 fn wire_tiles(start: IVec2, end: IVec2) -> impl Iterator<Item = IVec2> {
     let delta = end - start;
     // Either X or Y is zero, so the "normalized" vector is clamping the
@@ -1441,3 +3296,367 @@ fn wire_tiles(start: IVec2, end: IVec2) -> impl Iterator<Item = IVec2> {
 
     (0..=len).map(move |i| start + ray * i)
 }
+
+/// Writes a graph in Graphviz DOT format, see [`Circuit::export_dot`]. Edges are pairs of
+/// indices into `nodes`.
+fn write_dot<W: Write>(
+    mut out: W,
+    nodes: &[(ComponentType, IVec2)],
+    edges: &[(usize, usize)],
+) -> std::io::Result<()> {
+    writeln!(out, "digraph circuit {{")?;
+    let mut sources = Vec::new();
+    for (index, &(ty, position)) in nodes.iter().enumerate() {
+        writeln!(
+            out,
+            "    g{index} [label=\"{ty:?} ({}, {})\"];",
+            position.x, position.y
+        )?;
+        if ty == ComponentType::Switch {
+            sources.push(format!("g{index}"));
+        }
+    }
+    if !sources.is_empty() {
+        writeln!(out, "    {{ rank=source; {}; }}", sources.join("; "))?;
+    }
+    for (from, to) in edges {
+        writeln!(out, "    g{from} -> g{to};")?;
+    }
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+/// Groups `nodes` into the strongly connected components of the graph given by `edges`, using an
+/// iterative version of Tarjan's algorithm.
+///
+/// Components are returned in reverse topological order: no component has edges into a later one.
+fn strongly_connected_components<N>(nodes: &[N], edges: &HashMap<N, Vec<N>>) -> Vec<Vec<N>>
+where
+    N: Copy + Eq + std::hash::Hash,
+{
+    let mut index: HashMap<N, usize> = HashMap::new();
+    let mut low_link: HashMap<N, usize> = HashMap::new();
+    let mut stack: Vec<N> = Vec::new();
+    let mut on_stack: HashSet<N> = HashSet::new();
+    let mut components = Vec::new();
+
+    for &root in nodes {
+        if index.contains_key(&root) {
+            continue;
+        }
+        // Stands in for the recursion: each node with the position of its next edge to follow.
+        let mut call_stack = vec![(root, 0)];
+        while let Some(&(node, edge)) = call_stack.last() {
+            if edge == 0 {
+                index.insert(node, index.len());
+                low_link.insert(node, index[&node]);
+                stack.push(node);
+                on_stack.insert(node);
+            }
+            let successors = edges.get(&node).map(Vec::as_slice).unwrap_or(&[]);
+            if let Some(&next) = successors.get(edge) {
+                call_stack.last_mut().unwrap().1 += 1;
+                if !index.contains_key(&next) {
+                    call_stack.push((next, 0));
+                } else if on_stack.contains(&next) {
+                    let low = low_link[&node].min(index[&next]);
+                    low_link.insert(node, low);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                let low = low_link[&parent].min(low_link[&node]);
+                low_link.insert(parent, low);
+            }
+            if low_link[&node] == index[&node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Computes a right-angle path from `start` to `end` whose tiles avoid the given obstacle.
+///
+/// Returns the segments of the path as `(start, end)` pairs, in order. A direct or L-shaped path
+/// is preferred if it is clear; otherwise the path detours around the nearest side of the
+/// obstacle. If either endpoint lies inside the obstacle, no path exists and the result is empty.
+pub fn route_around(start: IVec2, end: IVec2, obstacle: &board::Board) -> Vec<(IVec2, IVec2)> {
+    let min = obstacle.position;
+    let max = obstacle.position + obstacle.size - IVec2::ONE;
+    let blocked = |a: IVec2, b: IVec2| {
+        let lo = a.min(b);
+        let hi = a.max(b);
+        lo.cmple(max).all() && hi.cmpge(min).all()
+    };
+    if blocked(start, start) || blocked(end, end) {
+        return Vec::new();
+    }
+
+    let candidates = [
+        vec![start, IVec2::new(end.x, start.y), end],
+        vec![start, IVec2::new(start.x, end.y), end],
+        vec![
+            start,
+            IVec2::new(start.x, min.y - 1),
+            IVec2::new(end.x, min.y - 1),
+            end,
+        ],
+        vec![
+            start,
+            IVec2::new(start.x, max.y + 1),
+            IVec2::new(end.x, max.y + 1),
+            end,
+        ],
+        vec![
+            start,
+            IVec2::new(min.x - 1, start.y),
+            IVec2::new(min.x - 1, end.y),
+            end,
+        ],
+        vec![
+            start,
+            IVec2::new(max.x + 1, start.y),
+            IVec2::new(max.x + 1, end.y),
+            end,
+        ],
+    ];
+
+    candidates
+        .iter()
+        .map(|points| simplify_path(points))
+        .filter(|path| path.iter().all(|&(a, b)| !blocked(a, b)))
+        .min_by_key(|path| {
+            path.iter()
+                .map(|&(a, b)| (b - a).abs().x + (b - a).abs().y)
+                .sum::<i32>()
+        })
+        .unwrap_or_default()
+}
+
+/// Converts a list of corner points into segments, dropping zero-length segments and merging
+/// collinear ones.
+fn simplify_path(points: &[IVec2]) -> Vec<(IVec2, IVec2)> {
+    let mut corners: Vec<IVec2> = Vec::with_capacity(points.len());
+    for &point in points {
+        if corners.last() == Some(&point) {
+            continue;
+        }
+        if corners.len() >= 2 {
+            let a = corners[corners.len() - 2];
+            let b = corners[corners.len() - 1];
+            if (wire_is_vertical(a, b) && wire_is_vertical(b, point))
+                || (wire_is_horizontal(a, b) && wire_is_horizontal(b, point))
+            {
+                corners.pop();
+            }
+        }
+        corners.push(point);
+    }
+    corners.windows(2).map(|v| (v[0], v[1])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        route_around, strongly_connected_components, write_dot, CircuitStats, ComponentType, Wire,
+    };
+    use crate::board::Board;
+    use crate::rect::{self, WireConnection};
+    use glam::IVec2;
+    use std::collections::HashMap;
+
+    fn wire(start: IVec2, end: IVec2) -> Wire {
+        Wire {
+            start,
+            end,
+            start_connection: WireConnection::Output,
+            end_connection: WireConnection::Pin,
+            cluster_index: 0,
+            instance: rect::Handle::detached(),
+        }
+    }
+
+    fn obstacle() -> Board {
+        Board {
+            position: IVec2::new(2, -2),
+            size: IVec2::new(3, 5),
+            color: [0.0; 4],
+            z_index: 1,
+        }
+    }
+
+    #[test]
+    fn stats_report() {
+        let stats = CircuitStats {
+            pins: 2,
+            flips: 1,
+            flops: 0,
+            switches: 1,
+            wires: 4,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "| Type | Count | Percent |\n\
+             |------|------:|--------:|\n\
+             | Pin | 2 | 25.0% |\n\
+             | Flip | 1 | 12.5% |\n\
+             | Flop | 0 | 0.0% |\n\
+             | Switch | 1 | 12.5% |\n\
+             | Wire | 4 | 50.0% |\n\
+             | **Total** | 8 | 100.0% |\n"
+        );
+        assert_eq!(
+            format!("{:?}", stats),
+            "CircuitStats(total: 8, components: 4, wires: 4)"
+        );
+        assert_eq!(
+            CircuitStats::default().to_string().lines().last(),
+            Some("| **Total** | 0 | 0.0% |")
+        );
+    }
+
+    #[test]
+    fn route_clear() {
+        let path = route_around(IVec2::new(0, 5), IVec2::new(10, 5), &obstacle());
+        assert_eq!(path, vec![(IVec2::new(0, 5), IVec2::new(10, 5))]);
+    }
+
+    #[test]
+    fn route_detour() {
+        let path = route_around(IVec2::new(0, 0), IVec2::new(10, 0), &obstacle());
+        assert_eq!(
+            path,
+            vec![
+                (IVec2::new(0, 0), IVec2::new(0, -3)),
+                (IVec2::new(0, -3), IVec2::new(10, -3)),
+                (IVec2::new(10, -3), IVec2::new(10, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn route_l_shape() {
+        let path = route_around(IVec2::new(0, 0), IVec2::new(3, 4), &obstacle());
+        assert_eq!(
+            path,
+            vec![
+                (IVec2::new(0, 0), IVec2::new(0, 4)),
+                (IVec2::new(0, 4), IVec2::new(3, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn route_blocked_endpoint() {
+        let path = route_around(IVec2::new(3, 0), IVec2::new(10, 0), &obstacle());
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn intersect_crossing_orientations() {
+        let horizontal = (IVec2::new(-2, 1), IVec2::new(3, 1));
+        let vertical = (IVec2::new(0, -4), IVec2::new(0, 2));
+        let expected = Some(IVec2::new(0, 1));
+        for (a, b) in [(horizontal, vertical), (vertical, horizontal)] {
+            for a in [wire(a.0, a.1), wire(a.1, a.0)] {
+                for b in [wire(b.0, b.1), wire(b.1, b.0)] {
+                    assert_eq!(a.intersects(&b), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn intersect_endpoints_and_misses() {
+        let horizontal = wire(IVec2::new(0, 0), IVec2::new(4, 0));
+        assert_eq!(
+            horizontal.intersects(&wire(IVec2::new(4, 0), IVec2::new(4, 3))),
+            Some(IVec2::new(4, 0))
+        );
+        assert_eq!(
+            horizontal.intersects(&wire(IVec2::new(5, -1), IVec2::new(5, 3))),
+            None
+        );
+        assert_eq!(
+            horizontal.intersects(&wire(IVec2::new(2, 1), IVec2::new(2, 3))),
+            None
+        );
+        assert_eq!(
+            horizontal.intersects(&wire(IVec2::new(2, 0), IVec2::new(6, 0))),
+            None
+        );
+    }
+
+    #[test]
+    fn wire_axis() {
+        let horizontal = wire(IVec2::new(4, 2), IVec2::new(-1, 2));
+        assert!(horizontal.is_horizontal());
+        assert!(!horizontal.is_vertical());
+        let vertical = wire(IVec2::new(0, 0), IVec2::new(0, 3));
+        assert!(vertical.is_vertical());
+        assert!(!vertical.is_horizontal());
+    }
+
+    #[test]
+    fn strongly_connected_components_finds_loops() {
+        // 0 -> 1 -> 2 -> 0 is a loop feeding 3, 4 feeds itself and 5 is alone.
+        let edges: HashMap<u32, Vec<u32>> =
+            [(0, vec![1]), (1, vec![2]), (2, vec![0, 3]), (4, vec![4])]
+                .into_iter()
+                .collect();
+        let mut components = strongly_connected_components(&[3, 0, 1, 2, 4, 5], &edges);
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        assert_eq!(components, vec![vec![3], vec![0, 1, 2], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn dot_output() {
+        let nodes = [
+            (ComponentType::Switch, IVec2::new(0, 0)),
+            (ComponentType::Flip, IVec2::new(2, -1)),
+            (ComponentType::Flop, IVec2::new(4, 0)),
+        ];
+        let mut out = Vec::new();
+        write_dot(&mut out, &nodes, &[(0, 1), (1, 2), (0, 2)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "digraph circuit {\n\
+             \x20   g0 [label=\"Switch (0, 0)\"];\n\
+             \x20   g1 [label=\"Flip (2, -1)\"];\n\
+             \x20   g2 [label=\"Flop (4, 0)\"];\n\
+             \x20   { rank=source; g0; }\n\
+             \x20   g0 -> g1;\n\
+             \x20   g1 -> g2;\n\
+             \x20   g0 -> g2;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn normalize_wire() {
+        let normalized = wire(IVec2::new(3, 5), IVec2::new(3, 1)).normalize();
+        assert_eq!(normalized.start, IVec2::new(3, 1));
+        assert_eq!(normalized.end, IVec2::new(3, 5));
+        // The connections follow their endpoints.
+        assert!(matches!(normalized.start_connection, WireConnection::Pin));
+        assert!(matches!(normalized.end_connection, WireConnection::Output));
+
+        let normalized = normalized.normalize();
+        assert_eq!(normalized.start, IVec2::new(3, 1));
+        assert_eq!(normalized.end, IVec2::new(3, 5));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::serializer::CircuitData;
+
+/// Holds a copied part of the circuit, so it can be pasted elsewhere.
+///
+/// The clipboard only lives for the current session, so pasting never brings back data that was
+/// copied before a restart.
+#[derive(Default)]
+pub struct Clipboard {
+    contents: Option<CircuitData>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether nothing has been copied, or the copy did not contain any component or wire.
+    pub fn is_empty(&self) -> bool {
+        self.contents.as_ref().is_none_or(CircuitData::is_empty)
+    }
+
+    pub fn contents(&self) -> Option<&CircuitData> {
+        self.contents.as_ref()
+    }
+
+    pub fn copy(&mut self, data: CircuitData) {
+        self.contents = Some(data);
+    }
+
+    pub fn clear(&mut self) {
+        self.contents = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clipboard;
+    use crate::serializer::{CircuitData, WireRecord};
+    use glam::IVec2;
+
+    #[test]
+    fn is_empty() {
+        let mut clipboard = Clipboard::new();
+        assert!(clipboard.is_empty());
+
+        clipboard.copy(CircuitData::default());
+        assert!(clipboard.is_empty());
+
+        clipboard.copy(CircuitData {
+            components: Vec::new(),
+            wires: vec![WireRecord {
+                start: IVec2::ZERO,
+                end: IVec2::X,
+            }],
+            ..Default::default()
+        });
+        assert!(!clipboard.is_empty());
+
+        clipboard.clear();
+        assert!(clipboard.is_empty());
+    }
+}
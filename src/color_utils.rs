@@ -0,0 +1,73 @@
+use glam::{Vec3, Vec4};
+
+/// Converts an RGB color to HSV: hue in degrees in `[0, 360)`, saturation and value in `[0, 1]`.
+pub fn rgb_to_hsv(rgb: Vec3) -> Vec3 {
+    let max = rgb.max_element();
+    let min = rgb.min_element();
+    let delta = max - min;
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == rgb.x {
+        60.0 * ((rgb.y - rgb.z) / delta)
+    } else if max == rgb.y {
+        60.0 * ((rgb.z - rgb.x) / delta + 2.0)
+    } else {
+        60.0 * ((rgb.x - rgb.y) / delta + 4.0)
+    };
+    let saturation = if max <= 0.0 { 0.0 } else { delta / max };
+    Vec3::new(hue.rem_euclid(360.0), saturation, max)
+}
+
+/// Converts an HSV color, as returned by [`rgb_to_hsv`], back to RGB. The hue wraps around.
+pub fn hsv_to_rgb(hsv: Vec3) -> Vec3 {
+    let hue = hsv.x.rem_euclid(360.0) / 60.0;
+    let chroma = hsv.z * hsv.y;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let rgb = match hue as u32 {
+        0 => Vec3::new(chroma, x, 0.0),
+        1 => Vec3::new(x, chroma, 0.0),
+        2 => Vec3::new(0.0, chroma, x),
+        3 => Vec3::new(0.0, x, chroma),
+        4 => Vec3::new(x, 0.0, chroma),
+        _ => Vec3::new(chroma, 0.0, x),
+    };
+    rgb + Vec3::splat(hsv.z - chroma)
+}
+
+/// Rotates the hue of an RGBA color by `degrees`, keeping its saturation, value and alpha.
+pub fn shift_hue(color: Vec4, degrees: f32) -> Vec4 {
+    let mut hsv = rgb_to_hsv(color.truncate());
+    hsv.x += degrees;
+    hsv_to_rgb(hsv).extend(color.w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hsv_to_rgb, rgb_to_hsv, shift_hue};
+    use glam::{Vec3, Vec4};
+
+    #[test]
+    fn hsv_round_trip() {
+        let colors = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.2, 0.7, 0.4),
+            Vec3::new(0.1, 0.3, 0.9),
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::ZERO,
+        ];
+        for rgb in colors {
+            assert!(hsv_to_rgb(rgb_to_hsv(rgb)).abs_diff_eq(rgb, 1e-5), "{rgb}");
+        }
+        assert!(rgb_to_hsv(Vec3::new(0.0, 1.0, 0.0)).abs_diff_eq(Vec3::new(120.0, 1.0, 1.0), 1e-5));
+    }
+
+    #[test]
+    fn hue_shift() {
+        let red = Vec4::new(1.0, 0.0, 0.0, 0.5);
+        assert!(shift_hue(red, 120.0).abs_diff_eq(Vec4::new(0.0, 1.0, 0.0, 0.5), 1e-5));
+        assert!(shift_hue(red, -120.0).abs_diff_eq(Vec4::new(0.0, 0.0, 1.0, 0.5), 1e-5));
+        assert!(shift_hue(red, 360.0).abs_diff_eq(red, 1e-5));
+        let gray = Vec4::new(0.5, 0.5, 0.5, 1.0);
+        assert!(shift_hue(gray, 90.0).abs_diff_eq(gray, 1e-5));
+    }
+}
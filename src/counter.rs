@@ -28,6 +28,13 @@ impl Counter {
         self.count += 1;
     }
 
+    /// Discards the ticks counted so far, e.g. after a slow frame that should not drag down the
+    /// next rate. The last rate is kept until a new one is measured.
+    pub fn reset(&mut self) {
+        self.since = Instant::now();
+        self.count = 0;
+    }
+
     pub fn rate(&self) -> f32 {
         self.rate
     }
@@ -3,17 +3,41 @@ mod outline;
 use self::outline::OutlineRenderer;
 use crate::circuit::{wire_direction, Circuit, ComponentType};
 use crate::direction::Direction;
+use crate::grid::GridAligner;
 use crate::rect::{self, Color, RectRenderer};
+use crate::serializer::RectI;
 use crate::viewport::Viewport;
 use crate::GraphicsContext;
 use glam::{IVec2, Vec2, Vec3, Vec4};
 
+/// Radians the view orbits per pixel of horizontal drag.
+const ORBIT_SENSITIVITY: f32 = 0.01;
+
+/// Color of the measurement line, chosen to stand out from wires in every theme.
+const MEASURE_COLOR: Vec4 = Vec4::new(1.0, 0.0, 1.0, 1.0);
+
+/// Color of the selection outline.
+const SELECTION_COLOR: Vec4 = Vec4::new(0.0, 0.8, 1.0, 1.0);
+
+/// Width of the selection outline, in tiles.
+const SELECTION_LINE_WIDTH: f32 = 0.08;
+
+/// The dash pattern of the selection outline, in tiles.
+const SELECTION_DASH_LENGTH: f32 = 0.3;
+const SELECTION_GAP_LENGTH: f32 = 0.2;
+
 pub struct CursorManager {
     rect_renderer: RectRenderer,
+    /// Draws the selection outline dashed.
+    selection_renderer: RectRenderer,
     outline_renderer: OutlineRenderer,
     current_state: CursorState,
     place_sprite: Sprite,
     place_orientation: Direction,
+    /// The edges of the selection, or of the area being selected; hidden when there is none.
+    selection_outline: [rect::Handle; 4],
+    /// The selection shown while not selecting or moving, see [`Self::show_selection`].
+    selection: Option<RectI>,
 }
 
 impl CursorManager {
@@ -21,13 +45,18 @@ impl CursorManager {
         let mut rect_renderer = RectRenderer::new(gfx, viewport);
         let place_sprite = Sprite::new(ComponentType::Pin, &mut rect_renderer);
         let outline_renderer = OutlineRenderer::new(gfx, viewport);
+        let mut selection_renderer = RectRenderer::new(gfx, viewport);
+        let selection_outline = [(); 4].map(|()| selection_renderer.insert(&Default::default()));
 
         Self {
             rect_renderer,
+            selection_renderer,
             place_sprite,
             outline_renderer,
             current_state: CursorState::Normal,
             place_orientation: Direction::North,
+            selection_outline,
+            selection: None,
         }
     }
 
@@ -35,19 +64,27 @@ impl CursorManager {
         &self.current_state
     }
 
-    pub fn update(&mut self, viewport: &mut Viewport, circuit: &Circuit) {
-        self.place_sprite.update(
-            viewport.cursor().tile(),
-            self.place_orientation,
-            &self.current_state,
-        );
+    pub fn update(&mut self, viewport: &mut Viewport, circuit: &Circuit, grid: &GridAligner) {
+        let place_position = grid.align(viewport.cursor().world_position);
+        self.place_sprite
+            .update(place_position, self.place_orientation, &self.current_state);
         match &mut self.current_state {
             CursorState::Normal => {}
             CursorState::Pan { last_position } => {
                 let position = viewport.cursor().screen_position;
-                let delta = (position - *last_position) * Vec2::new(1.0, -1.0);
-                let camera = viewport.camera_mut();
-                camera.pan -= delta / camera.zoom;
+                viewport
+                    .camera_mut()
+                    .pan_by_screen_delta(position - *last_position);
+
+                *last_position = position;
+            }
+            CursorState::Orbit {
+                center,
+                last_position,
+            } => {
+                let position = viewport.cursor().screen_position;
+                let angle = (position.x - last_position.x) * ORBIT_SENSITIVITY;
+                viewport.camera_mut().orbit(*center, angle);
 
                 *last_position = position;
             }
@@ -58,7 +95,7 @@ impl CursorManager {
                 end_pin,
                 wire,
             } => {
-                let delta = viewport.cursor().tile() - *start_position;
+                let delta = place_position - *start_position;
 
                 let size;
                 if delta.x.abs() > delta.y.abs() {
@@ -106,6 +143,39 @@ impl CursorManager {
                     .into(),
                 );
             }
+            CursorState::Measure {
+                start,
+                end,
+                horizontal,
+                vertical,
+            } => {
+                *end = viewport.cursor().tile();
+                // Drawn as the Manhattan path: along X first, then along Y.
+                let corner = IVec2::new(end.x, start.y);
+                horizontal.set(&measure_segment(*start, corner));
+                vertical.set(&measure_segment(corner, *end));
+            }
+            CursorState::Erase { .. } => {}
+            CursorState::Select { anchor, current } => {
+                *current = viewport.cursor().tile();
+                let bounds = tile_bounds(*anchor, *current);
+                set_outline(&self.selection_outline, Some(bounds));
+            }
+            CursorState::MoveSelection {
+                bounds,
+                start,
+                current,
+            } => {
+                *current = viewport.cursor().tile();
+                let offset = *current - *start;
+                set_outline(
+                    &self.selection_outline,
+                    Some(RectI {
+                        min: bounds.min + offset,
+                        max: bounds.max + offset,
+                    }),
+                );
+            }
         }
 
         let valid_place = match &self.current_state {
@@ -116,11 +186,9 @@ impl CursorManager {
             } => circuit.can_place_wire(start_position, end_position),
             _ => match self.place_type() {
                 ComponentType::Pin => true,
-                other_type => circuit.can_place_component(
-                    other_type,
-                    viewport.cursor().tile(),
-                    self.place_orientation,
-                ),
+                other_type => {
+                    circuit.can_place_component(other_type, place_position, self.place_orientation)
+                }
             },
         };
 
@@ -141,6 +209,14 @@ impl CursorManager {
     ) {
         self.rect_renderer
             .draw(viewport, encoder, frame_view, depth_view);
+        self.selection_renderer.draw_all_dashed(
+            SELECTION_DASH_LENGTH,
+            SELECTION_GAP_LENGTH,
+            viewport,
+            encoder,
+            frame_view,
+            depth_view,
+        );
         self.outline_renderer
             .draw(viewport, encoder, frame_view, depth_view);
     }
@@ -151,8 +227,16 @@ impl CursorManager {
         });
     }
 
-    pub fn start_place_wire(&mut self, viewport: &Viewport) {
-        let start_position = viewport.cursor().tile();
+    /// Starts orbiting the view around the world position under the cursor.
+    pub fn start_orbit(&mut self, viewport: &Viewport) {
+        self.replace(CursorState::Orbit {
+            center: viewport.cursor().world_position,
+            last_position: viewport.cursor().screen_position,
+        });
+    }
+
+    pub fn start_place_wire(&mut self, viewport: &Viewport, grid: &GridAligner) {
+        let start_position = grid.align(viewport.cursor().world_position);
         let start_pin = self.rect_renderer.insert(
             &rect::Pin {
                 position: start_position,
@@ -186,8 +270,71 @@ impl CursorManager {
         })
     }
 
+    pub fn start_measure(&mut self, viewport: &Viewport) {
+        let start = viewport.cursor().tile();
+        let horizontal = self.rect_renderer.insert(&measure_segment(start, start));
+        let vertical = self.rect_renderer.insert(&measure_segment(start, start));
+        self.replace(CursorState::Measure {
+            start,
+            end: start,
+            horizontal,
+            vertical,
+        });
+    }
+
+    /// Starts selecting the tiles between the one under the cursor and wherever the cursor ends
+    /// up.
+    pub fn start_select(&mut self, viewport: &Viewport) {
+        let anchor = viewport.cursor().tile();
+        self.replace(CursorState::Select {
+            anchor,
+            current: anchor,
+        });
+    }
+
+    /// Starts dragging the selected tiles in `bounds` along with the cursor.
+    pub fn start_move_selection(&mut self, viewport: &Viewport, bounds: RectI) {
+        let start = viewport.cursor().tile();
+        self.replace(CursorState::MoveSelection {
+            bounds,
+            start,
+            current: start,
+        });
+    }
+
+    /// Outlines `selection` from now on, or nothing if `None`.
+    pub fn show_selection(&mut self, selection: Option<RectI>) {
+        self.selection = selection;
+        set_outline(&self.selection_outline, selection);
+    }
+
+    /// Starts erasing at the tile under the cursor, which the caller is expected to clear.
+    pub fn start_erase(&mut self, viewport: &Viewport) {
+        self.replace(CursorState::Erase {
+            last_position: viewport.cursor().tile(),
+        });
+    }
+
+    /// The tiles the cursor crossed since the previous call while erasing, in order. Empty in
+    /// other states.
+    ///
+    /// Fast drags move several tiles between frames; the tiles in between are filled in along a
+    /// straight line.
+    pub fn erase_path(&mut self, viewport: &Viewport) -> Vec<IVec2> {
+        match &mut self.current_state {
+            CursorState::Erase { last_position } => {
+                let position = viewport.cursor().tile();
+                let path = line_tiles(*last_position, position);
+                *last_position = position;
+                path.into_iter().skip(1).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
     pub fn end(&mut self) {
         self.replace(CursorState::Normal);
+        set_outline(&self.selection_outline, self.selection);
     }
 
     pub fn place_type(&self) -> ComponentType {
@@ -218,6 +365,10 @@ pub enum CursorState {
     Pan {
         last_position: Vec2,
     },
+    Orbit {
+        center: Vec2,
+        last_position: Vec2,
+    },
     PlaceWire {
         start_position: IVec2,
         end_position: IVec2,
@@ -225,6 +376,130 @@ pub enum CursorState {
         end_pin: rect::Handle,
         wire: rect::Handle,
     },
+    Measure {
+        start: IVec2,
+        end: IVec2,
+        horizontal: rect::Handle,
+        vertical: rect::Handle,
+    },
+    /// Deleting everything under the cursor while dragging, see [`CursorManager::erase_path`].
+    Erase {
+        last_position: IVec2,
+    },
+    /// Dragging out a selection rectangle from `anchor` to `current`, both included.
+    Select {
+        anchor: IVec2,
+        current: IVec2,
+    },
+    /// Dragging the selection in `bounds` by `current - start`.
+    MoveSelection {
+        bounds: RectI,
+        start: IVec2,
+        current: IVec2,
+    },
+}
+
+impl CursorState {
+    /// The distance label shown while measuring, e.g. `ΔX=3, ΔY=-2, L=5`.
+    pub fn measure_label(&self) -> Option<String> {
+        match self {
+            &Self::Measure { start, end, .. } => Some(measure_label(start, end)),
+            _ => None,
+        }
+    }
+}
+
+fn measure_label(start: IVec2, end: IVec2) -> String {
+    let delta = end - start;
+    format!(
+        "\u{394}X={}, \u{394}Y={}, L={}",
+        delta.x,
+        delta.y,
+        delta.x.abs() + delta.y.abs()
+    )
+}
+
+/// The tiles on the line from `start` to `end`, both included, by Bresenham's algorithm.
+fn line_tiles(start: IVec2, end: IVec2) -> Vec<IVec2> {
+    let delta = (end - start).abs();
+    let step = (end - start).signum();
+    let mut error = delta.x - delta.y;
+    let mut position = start;
+    let mut tiles = vec![position];
+    while position != end {
+        let doubled = 2 * error;
+        if doubled > -delta.y {
+            error -= delta.y;
+            position.x += step.x;
+        }
+        if doubled < delta.x {
+            error += delta.x;
+            position.y += step.y;
+        }
+        tiles.push(position);
+    }
+    tiles
+}
+
+/// The tiles from `a` to `b`, both included, in any order.
+pub fn tile_bounds(a: IVec2, b: IVec2) -> RectI {
+    RectI {
+        min: a.min(b),
+        max: a.max(b) + IVec2::ONE,
+    }
+}
+
+/// The bottom, right, top and left edges of `bounds`, drawn just inside it. They are dashed
+/// along their length when drawn, see [`RectRenderer::draw_all_dashed`].
+fn selection_edges(bounds: RectI) -> [rect::Rect; 4] {
+    let min = bounds.min.as_vec2();
+    let size = (bounds.max - bounds.min).as_vec2();
+    let edge = |position: Vec2, size: Vec2| rect::Rect {
+        position,
+        z_index: u8::MAX,
+        size,
+        color: Color::Fixed(SELECTION_COLOR),
+        shape: rect::Shape::Square,
+    };
+    let width = SELECTION_LINE_WIDTH;
+    [
+        edge(min, Vec2::new(size.x, width)),
+        edge(
+            Vec2::new(min.x + size.x - width, min.y),
+            Vec2::new(width, size.y),
+        ),
+        edge(
+            Vec2::new(min.x, min.y + size.y - width),
+            Vec2::new(size.x, width),
+        ),
+        edge(min, Vec2::new(width, size.y)),
+    ]
+}
+
+fn set_outline(outline: &[rect::Handle; 4], bounds: Option<RectI>) {
+    match bounds {
+        Some(bounds) => {
+            for (handle, edge) in outline.iter().zip(selection_edges(bounds)) {
+                handle.set(&edge);
+            }
+        }
+        None => {
+            for handle in outline {
+                handle.set(&Default::default());
+            }
+        }
+    }
+}
+
+fn measure_segment(start: IVec2, end: IVec2) -> rect::Rect {
+    rect::Wire {
+        start,
+        end,
+        start_connection: Default::default(),
+        end_connection: Default::default(),
+        color: Color::Fixed(MEASURE_COLOR),
+    }
+    .into()
 }
 
 enum Sprite {
@@ -285,7 +560,12 @@ impl Sprite {
         let visible = match current_state {
             CursorState::Normal => true,
             CursorState::Pan { .. } => false,
+            CursorState::Orbit { .. } => false,
             CursorState::PlaceWire { .. } => false,
+            CursorState::Measure { .. } => false,
+            CursorState::Erase { .. } => false,
+            CursorState::Select { .. } => false,
+            CursorState::MoveSelection { .. } => false,
         };
         match self {
             Self::Pin { pin } => {
@@ -390,3 +670,61 @@ impl Sprite {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{line_tiles, measure_label, selection_edges, tile_bounds};
+    use crate::serializer::RectI;
+    use glam::{IVec2, Vec2};
+
+    #[test]
+    fn selection_outline_surrounds_tiles() {
+        let bounds = tile_bounds(IVec2::new(3, -1), IVec2::new(1, 2));
+        assert_eq!(
+            bounds,
+            RectI {
+                min: IVec2::new(1, -1),
+                max: IVec2::new(4, 3),
+            }
+        );
+        for edge in selection_edges(bounds) {
+            let min = edge.position;
+            let max = edge.position + edge.size;
+            assert!(min.cmpge(Vec2::new(1.0, -1.0)).all());
+            assert!(max.cmple(Vec2::new(4.0, 3.0)).all());
+            // Each edge spans a full side.
+            assert!(edge.size.x == 3.0 || edge.size.y == 4.0);
+        }
+    }
+
+    #[test]
+    fn measure_label_is_manhattan() {
+        assert_eq!(
+            measure_label(IVec2::new(1, 5), IVec2::new(4, 3)),
+            "\u{394}X=3, \u{394}Y=-2, L=5"
+        );
+    }
+
+    #[test]
+    fn line_tiles_has_no_gaps() {
+        assert_eq!(
+            line_tiles(IVec2::new(2, 2), IVec2::new(2, 2)),
+            [IVec2::new(2, 2)]
+        );
+        assert_eq!(
+            line_tiles(IVec2::new(0, 0), IVec2::new(4, -2)),
+            [
+                IVec2::new(0, 0),
+                IVec2::new(1, 0),
+                IVec2::new(2, -1),
+                IVec2::new(3, -1),
+                IVec2::new(4, -2),
+            ]
+        );
+        let tiles = line_tiles(IVec2::new(-3, 5), IVec2::new(1, -6));
+        assert_eq!(tiles.len(), 12);
+        assert!(tiles
+            .windows(2)
+            .all(|pair| (pair[1] - pair[0]).abs().max_element() == 1));
+    }
+}
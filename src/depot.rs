@@ -56,6 +56,10 @@ impl<T> Depot<T> {
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.items.iter().map(|(&handle, item)| (handle, item))
+    }
 }
 
 impl<'a, T> ops::Index<&'a Handle> for Depot<T> {
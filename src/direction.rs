@@ -1,6 +1,7 @@
-use glam::{mat2, Mat2, Vec2};
+use glam::{mat2, IVec2, Mat2, Vec2};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     East,
     North,
@@ -21,6 +22,25 @@ impl Direction {
         self.rotate(Relative::Opposite)
     }
 
+    /// The offset to the neighboring tile in this direction.
+    pub fn unit(self) -> IVec2 {
+        match self {
+            Self::East => IVec2::X,
+            Self::North => IVec2::Y,
+            Self::West => -IVec2::X,
+            Self::South => -IVec2::Y,
+        }
+    }
+
+    /// Reflects the direction across a vertical axis, swapping east and west.
+    pub fn mirror_horizontal(self) -> Self {
+        match self {
+            Self::East => Self::West,
+            Self::West => Self::East,
+            other => other,
+        }
+    }
+
     pub fn rotate(self, relative: Relative) -> Self {
         match (self, relative) {
             (Self::East, Relative::Same) => Self::East,
@@ -88,3 +108,26 @@ impl Relative {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    /// The unit vector pointing in the positive direction of this axis.
+    pub fn unit(self) -> IVec2 {
+        match self {
+            Self::Horizontal => IVec2::X,
+            Self::Vertical => IVec2::Y,
+        }
+    }
+
+    pub fn perpendicular(self) -> Self {
+        match self {
+            Self::Horizontal => Self::Vertical,
+            Self::Vertical => Self::Horizontal,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+use winit::event_loop::ControlFlow;
+
+/// The frame interval used while the window is not focused (10 FPS).
+const UNFOCUSED_INTERVAL: Duration = Duration::from_millis(100);
+/// The frame rate cap used while the window is focused, unless changed.
+pub const DEFAULT_TARGET_FPS: f32 = 60.0;
+
+/// Throttles redraws to a target frame rate, and further while the window is in the background.
+///
+/// Between frames the event loop blocks until the next frame is due, so idle circuits do not
+/// spin the CPU and GPU. While unfocused, the simulation keeps running at a low rate.
+#[derive(Debug, Clone)]
+pub struct FpsLimiter {
+    focused: bool,
+    /// When the next frame is due.
+    next_frame: Instant,
+    target_fps: f32,
+}
+
+impl Default for FpsLimiter {
+    fn default() -> Self {
+        Self {
+            focused: true,
+            next_frame: Instant::now(),
+            target_fps: DEFAULT_TARGET_FPS,
+        }
+    }
+}
+
+impl FpsLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Caps the frame rate while focused. Zero or less removes the cap, redrawing as fast as the
+    /// swapchain allows.
+    pub fn set_target_fps(&mut self, target_fps: f32) {
+        self.target_fps = target_fps;
+    }
+
+    /// Schedules the next frame, to be called as a frame starts.
+    pub fn frame_started(&mut self) {
+        self.frame_started_at(Instant::now());
+    }
+
+    /// The next frame is due one interval after the previous one was, so the time spent drawing
+    /// counts towards the interval. After a stall, the next frame is due right away, without
+    /// catching up on the frames that were missed.
+    fn frame_started_at(&mut self, now: Instant) {
+        self.next_frame = match self.frame_interval() {
+            Some(interval) => (self.next_frame + interval).max(now),
+            None => now,
+        };
+    }
+
+    pub fn should_redraw(&self) -> bool {
+        match self.frame_interval() {
+            Some(_) => Instant::now() >= self.next_frame,
+            None => true,
+        }
+    }
+
+    pub fn control_flow(&self) -> ControlFlow {
+        match self.frame_interval() {
+            Some(_) => ControlFlow::WaitUntil(self.next_frame),
+            None => ControlFlow::Poll,
+        }
+    }
+
+    /// The minimum time between frames, if any.
+    fn frame_interval(&self) -> Option<Duration> {
+        if !self.focused {
+            Some(UNFOCUSED_INTERVAL)
+        } else if self.target_fps > 0.0 {
+            Some(Duration::from_secs_f32(1.0 / self.target_fps))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FpsLimiter, UNFOCUSED_INTERVAL};
+    use std::time::{Duration, Instant};
+    use winit::event_loop::ControlFlow;
+
+    #[test]
+    fn frame_interval() {
+        let mut limiter = FpsLimiter::new();
+        limiter.set_target_fps(50.0);
+        assert_eq!(limiter.frame_interval(), Some(Duration::from_millis(20)));
+        limiter.set_target_fps(0.0);
+        assert_eq!(limiter.frame_interval(), None);
+        limiter.set_focused(false);
+        assert_eq!(limiter.frame_interval(), Some(UNFOCUSED_INTERVAL));
+    }
+
+    #[test]
+    fn control_flow() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(20);
+        let mut limiter = FpsLimiter::new();
+        limiter.set_target_fps(50.0);
+        limiter.next_frame = start;
+
+        // A frame that starts on time is due one interval after the previous deadline, however
+        // long the previous frame took to draw.
+        limiter.frame_started_at(start + Duration::from_millis(1));
+        assert_eq!(
+            limiter.control_flow(),
+            ControlFlow::WaitUntil(start + interval)
+        );
+        limiter.frame_started_at(start + interval + Duration::from_millis(15));
+        assert_eq!(
+            limiter.control_flow(),
+            ControlFlow::WaitUntil(start + 2 * interval)
+        );
+
+        // After a stall, the next frame is due right away, without catching up.
+        let late = start + 10 * interval;
+        limiter.frame_started_at(late);
+        assert_eq!(limiter.control_flow(), ControlFlow::WaitUntil(late));
+
+        limiter.set_target_fps(0.0);
+        assert_eq!(limiter.control_flow(), ControlFlow::Poll);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::screen_bars::{self, Bar, ScreenBars};
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use glam::{Vec2, Vec4};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The number of frames shown in the graph.
+pub const SAMPLE_COUNT: usize = 120;
+
+const BAR_WIDTH: f32 = 2.0;
+const BAR_SPACING: f32 = 1.0;
+const PIXELS_PER_MS: f32 = 2.0;
+const MAX_BAR_HEIGHT: f32 = 100.0;
+const MARGIN: f32 = 8.0;
+
+const FAST_FRAME: Duration = Duration::from_millis(16);
+const SLOW_FRAME: Duration = Duration::from_millis(33);
+
+/// The durations of the most recent frames.
+#[derive(Debug, Clone)]
+pub struct FrameTimings {
+    last_frame: Option<Instant>,
+    samples: VecDeque<Duration>,
+}
+
+impl Default for FrameTimings {
+    fn default() -> Self {
+        Self {
+            last_frame: None,
+            samples: VecDeque::with_capacity(SAMPLE_COUNT),
+        }
+    }
+}
+
+impl FrameTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the time elapsed since the previous call.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            self.push(now - last_frame);
+        }
+        self.last_frame = Some(now);
+    }
+
+    pub fn push(&mut self, duration: Duration) {
+        if self.samples.len() == SAMPLE_COUNT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    /// The recorded durations, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Green for frames faster than 60 FPS, red for frames slower than 30 FPS, yellow in between.
+    pub fn color(duration: Duration) -> Vec4 {
+        if duration < FAST_FRAME {
+            Vec4::new(0.0, 0.8, 0.0, 0.8)
+        } else if duration > SLOW_FRAME {
+            Vec4::new(0.9, 0.0, 0.0, 0.8)
+        } else {
+            Vec4::new(0.9, 0.8, 0.0, 0.8)
+        }
+    }
+}
+
+/// Plots the last [`SAMPLE_COUNT`] frame durations as a bar chart in the bottom-left corner of
+/// the window.
+pub struct FrameTimingGraph {
+    bars: ScreenBars,
+    timings: FrameTimings,
+    pub visible: bool,
+}
+
+impl FrameTimingGraph {
+    pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> Self {
+        Self {
+            bars: ScreenBars::new(gfx, viewport, SAMPLE_COUNT),
+            timings: FrameTimings::new(),
+            visible: false,
+        }
+    }
+
+    /// Records a frame. Frames are recorded even while hidden, so the graph is full when shown.
+    pub fn tick(&mut self) {
+        self.timings.tick();
+    }
+
+    pub fn draw(
+        &mut self,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let window_height = viewport.size().y;
+        let bars: Vec<Bar> = self
+            .timings
+            .samples()
+            .enumerate()
+            .map(|(index, duration)| {
+                let height =
+                    (duration.as_secs_f32() * 1000.0 * PIXELS_PER_MS).clamp(1.0, MAX_BAR_HEIGHT);
+                let position = Vec2::new(
+                    MARGIN + index as f32 * (BAR_WIDTH + BAR_SPACING),
+                    window_height - MARGIN - height,
+                );
+                screen_bars::bar(
+                    position,
+                    Vec2::new(BAR_WIDTH, height),
+                    FrameTimings::color(duration),
+                )
+            })
+            .collect();
+        self.bars.draw(&bars, viewport, encoder, frame_view);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameTimings, SAMPLE_COUNT};
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_last_samples() {
+        let mut timings = FrameTimings::new();
+        for ms in 0..SAMPLE_COUNT as u64 + 10 {
+            timings.push(Duration::from_millis(ms));
+        }
+        let samples: Vec<Duration> = timings.samples().collect();
+        assert_eq!(samples.len(), SAMPLE_COUNT);
+        assert_eq!(samples[0], Duration::from_millis(10));
+        assert_eq!(
+            samples[SAMPLE_COUNT - 1],
+            Duration::from_millis(SAMPLE_COUNT as u64 + 9)
+        );
+    }
+
+    #[test]
+    fn colors_by_duration() {
+        let fast = FrameTimings::color(Duration::from_millis(10));
+        let medium = FrameTimings::color(Duration::from_millis(20));
+        let slow = FrameTimings::color(Duration::from_millis(40));
+        assert!(fast.y > fast.x);
+        assert!(slow.x > slow.y);
+        assert_ne!(medium, fast);
+        assert_ne!(medium, slow);
+    }
+}
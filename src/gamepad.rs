@@ -0,0 +1,156 @@
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
+use winit::event::{AxisId, DeviceEvent, DeviceId};
+
+/// Maps raw device axes to camera controls.
+///
+/// The default axis ids are those of the left stick (pan) and the right stick Y axis (zoom) on
+/// Linux, where sticks report values in the `i16` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GamepadConfig {
+    pub pan_x_axis: AxisId,
+    pub pan_y_axis: AxisId,
+    pub zoom_axis: AxisId,
+    /// Scales raw pan axis values, so that a fully tilted stick gives 1.
+    pub pan_sensitivity: f32,
+    /// Scales raw zoom axis values, so that a fully tilted stick gives 1.
+    pub zoom_sensitivity: f32,
+    /// Scaled values closer to zero than this are ignored, to hide stick drift.
+    pub dead_zone: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            pan_x_axis: 0,
+            pan_y_axis: 1,
+            zoom_axis: 4,
+            pan_sensitivity: 1.0 / 32768.0,
+            zoom_sensitivity: 1.0 / 32768.0,
+            dead_zone: 0.15,
+        }
+    }
+}
+
+/// Tracks the last reported value of every gamepad axis, per device.
+#[derive(Debug, Clone, Default)]
+pub struct Gamepad {
+    pub config: GamepadConfig,
+    axes: HashMap<(DeviceId, AxisId), f64>,
+    /// Mice also report their movement as axis motion; events from them are ignored.
+    mice: HashSet<DeviceId>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_device_event(&mut self, device_id: DeviceId, event: &DeviceEvent) {
+        match *event {
+            DeviceEvent::MouseMotion { .. } | DeviceEvent::MouseWheel { .. } => {
+                self.mice.insert(device_id);
+                self.axes
+                    .retain(|&(device, _axis), _value| device != device_id);
+            }
+            DeviceEvent::Motion { axis, value } if !self.mice.contains(&device_id) => {
+                self.axes.insert((device_id, axis), value);
+            }
+            DeviceEvent::Removed => {
+                self.axes
+                    .retain(|&(device, _axis), _value| device != device_id);
+                self.mice.remove(&device_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// The pan direction, in world orientation (Y pointing up).
+    pub fn pan(&self) -> Vec2 {
+        let x = self.axis(self.config.pan_x_axis, self.config.pan_sensitivity);
+        // Sticks report "up" as negative values.
+        let y = -self.axis(self.config.pan_y_axis, self.config.pan_sensitivity);
+        Vec2::new(x, y)
+    }
+
+    /// Positive to zoom in, negative to zoom out. Pushing the stick forward zooms in.
+    pub fn zoom(&self) -> f32 {
+        -self.axis(self.config.zoom_axis, self.config.zoom_sensitivity)
+    }
+
+    /// The value of `axis` on the device tilting it the furthest.
+    fn axis(&self, axis: AxisId, sensitivity: f32) -> f32 {
+        let value = self
+            .axes
+            .iter()
+            .filter(|((_device, id), _value)| *id == axis)
+            .map(|(_key, &value)| value)
+            .fold(
+                0.0,
+                |max: f64, value| {
+                    if value.abs() > max.abs() {
+                        value
+                    } else {
+                        max
+                    }
+                },
+            ) as f32
+            * sensitivity;
+        if value.abs() < self.config.dead_zone {
+            0.0
+        } else {
+            value.clamp(-1.0, 1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gamepad;
+    use glam::Vec2;
+    use winit::event::{DeviceEvent, DeviceId};
+
+    #[test]
+    fn maps_axes_with_dead_zone() {
+        let device = unsafe { DeviceId::dummy() };
+        let mut gamepad = Gamepad::new();
+        gamepad.handle_device_event(
+            device,
+            &DeviceEvent::Motion {
+                axis: 0,
+                value: 1000.0,
+            },
+        );
+        gamepad.handle_device_event(
+            device,
+            &DeviceEvent::Motion {
+                axis: 1,
+                value: -32768.0,
+            },
+        );
+        gamepad.handle_device_event(
+            device,
+            &DeviceEvent::Motion {
+                axis: 4,
+                value: 16384.0,
+            },
+        );
+        assert_eq!(gamepad.pan(), Vec2::new(0.0, 1.0));
+        assert_eq!(gamepad.zoom(), -0.5);
+    }
+
+    #[test]
+    fn ignores_mouse_motion() {
+        let device = unsafe { DeviceId::dummy() };
+        let mut gamepad = Gamepad::new();
+        gamepad.handle_device_event(device, &DeviceEvent::MouseMotion { delta: (5.0, 0.0) });
+        gamepad.handle_device_event(
+            device,
+            &DeviceEvent::Motion {
+                axis: 0,
+                value: 30000.0,
+            },
+        );
+        assert_eq!(gamepad.pan(), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::circuit::ComponentType;
+use crate::direction::{Direction, Relative};
+use crate::logic_gate::GateKind;
+use crate::rect::PinKind;
+use glam::IVec2;
+
+/// The sides a component connects to wires on, relative to its orientation, and what it does
+/// with each.
+pub type PinLayout = &'static [(PinKind, Relative)];
+
+/// Pins join the wires on every side into one net.
+pub const PIN: PinLayout = &[
+    (PinKind::Bidirectional, Relative::Same),
+    (PinKind::Bidirectional, Relative::Right),
+    (PinKind::Bidirectional, Relative::Opposite),
+    (PinKind::Bidirectional, Relative::Left),
+];
+pub const FLIP: PinLayout = &[
+    (PinKind::Input, Relative::Right),
+    (PinKind::Input, Relative::Opposite),
+    (PinKind::Input, Relative::Left),
+    (PinKind::Output, Relative::Same),
+];
+pub const FLOP: PinLayout = &[
+    (PinKind::Input, Relative::Opposite),
+    (PinKind::Output, Relative::Same),
+];
+/// The input of a switch is toggled by interacting with it, not by a wire.
+pub const SWITCH: PinLayout = &[(PinKind::Output, Relative::Same)];
+
+/// The layout of each component type, indexed by `ComponentType as usize`.
+pub const LAYOUTS: [PinLayout; 4] = [PIN, FLIP, FLOP, SWITCH];
+
+pub fn pin_layout(ty: ComponentType) -> PinLayout {
+    LAYOUTS[ty as usize]
+}
+
+/// The sides of a component of type `ty` facing `orientation` that connect to wires.
+pub fn pin_directions(ty: ComponentType, orientation: Direction) -> Vec<(PinKind, Direction)> {
+    pin_layout(ty)
+        .iter()
+        .map(|&(kind, relative)| (kind, orientation.rotate(relative)))
+        .collect()
+}
+
+/// The tiles next to a component of type `ty` at `position` facing `orientation` that its
+/// pins connect to.
+pub fn pin_positions(
+    ty: ComponentType,
+    position: IVec2,
+    orientation: Direction,
+) -> Vec<(PinKind, IVec2)> {
+    pin_directions(ty, orientation)
+        .into_iter()
+        .map(|(kind, direction)| (kind, position + direction.unit()))
+        .collect()
+}
+
+/// The components and wires of a gate, relative to its origin. Every component faces east;
+/// inputs are on the west edge and the output is on the east edge.
+#[derive(Debug)]
+pub struct GateLayout {
+    /// The width and height of the tiles used by the gate.
+    pub size: IVec2,
+    pub components: &'static [(ComponentType, IVec2)],
+    pub wires: &'static [(IVec2, IVec2)],
+    pub inputs: &'static [IVec2],
+    pub output: IVec2,
+}
+
+const fn at(x: i32, y: i32) -> IVec2 {
+    IVec2::new(x, y)
+}
+
+/// A single flip.
+const NOT: GateLayout = GateLayout {
+    size: at(3, 1),
+    components: &[(ComponentType::Flip, at(1, 0))],
+    wires: &[(at(0, 0), at(1, 0)), (at(1, 0), at(2, 0))],
+    inputs: &[at(0, 0)],
+    output: at(2, 0),
+};
+
+/// Two inputs through flops (buffers) or flips (inverters) driving one net.
+const MERGE_WIRES: &[(IVec2, IVec2)] = &[
+    (at(0, 0), at(1, 0)),
+    (at(1, 0), at(2, 0)),
+    (at(0, 2), at(1, 2)),
+    (at(1, 2), at(2, 2)),
+    (at(2, 2), at(2, 0)),
+    (at(2, 0), at(3, 0)),
+];
+
+/// A merge followed by a flip.
+const INVERTED_MERGE_WIRES: &[(IVec2, IVec2)] = &[
+    (at(0, 0), at(1, 0)),
+    (at(1, 0), at(2, 0)),
+    (at(0, 2), at(1, 2)),
+    (at(1, 2), at(2, 2)),
+    (at(2, 2), at(2, 0)),
+    (at(2, 0), at(3, 0)),
+    (at(3, 0), at(4, 0)),
+];
+
+const OR: GateLayout = GateLayout {
+    size: at(4, 3),
+    components: &[
+        (ComponentType::Flop, at(1, 0)),
+        (ComponentType::Flop, at(1, 2)),
+    ],
+    wires: MERGE_WIRES,
+    inputs: &[at(0, 0), at(0, 2)],
+    output: at(3, 0),
+};
+
+/// Either input low: the merge of the inverted inputs.
+const NAND: GateLayout = GateLayout {
+    size: at(4, 3),
+    components: &[
+        (ComponentType::Flip, at(1, 0)),
+        (ComponentType::Flip, at(1, 2)),
+    ],
+    wires: MERGE_WIRES,
+    inputs: &[at(0, 0), at(0, 2)],
+    output: at(3, 0),
+};
+
+const NOR: GateLayout = GateLayout {
+    size: at(5, 3),
+    components: &[
+        (ComponentType::Flop, at(1, 0)),
+        (ComponentType::Flop, at(1, 2)),
+        (ComponentType::Flip, at(3, 0)),
+    ],
+    wires: INVERTED_MERGE_WIRES,
+    inputs: &[at(0, 0), at(0, 2)],
+    output: at(4, 0),
+};
+
+/// Neither input low.
+const AND: GateLayout = GateLayout {
+    size: at(5, 3),
+    components: &[
+        (ComponentType::Flip, at(1, 0)),
+        (ComponentType::Flip, at(1, 2)),
+        (ComponentType::Flip, at(3, 0)),
+    ],
+    wires: INVERTED_MERGE_WIRES,
+    inputs: &[at(0, 0), at(0, 2)],
+    output: at(4, 0),
+};
+
+/// The inverse of NOR(A, B) merged with AND(A, B). The AND output crosses the NOR merge
+/// without connecting to it.
+const XOR: GateLayout = GateLayout {
+    size: at(10, 4),
+    components: &[
+        (ComponentType::Flop, at(1, 0)),
+        (ComponentType::Flip, at(1, 1)),
+        (ComponentType::Flip, at(1, 2)),
+        (ComponentType::Flop, at(1, 3)),
+        (ComponentType::Flip, at(3, 1)),
+        (ComponentType::Flip, at(6, 0)),
+        (ComponentType::Flip, at(8, 0)),
+    ],
+    wires: &[
+        // Inputs, each to a flop and a flip.
+        (at(0, 0), at(1, 0)),
+        (at(0, 0), at(0, 1)),
+        (at(0, 1), at(1, 1)),
+        (at(0, 3), at(1, 3)),
+        (at(0, 3), at(0, 2)),
+        (at(0, 2), at(1, 2)),
+        // AND(A, B).
+        (at(1, 1), at(2, 1)),
+        (at(1, 2), at(2, 2)),
+        (at(2, 1), at(2, 2)),
+        (at(2, 1), at(3, 1)),
+        // NOR(A, B).
+        (at(1, 0), at(5, 0)),
+        (at(1, 3), at(5, 3)),
+        (at(5, 0), at(5, 3)),
+        (at(5, 0), at(6, 0)),
+        // Inverse of the merge of both.
+        (at(3, 1), at(7, 1)),
+        (at(6, 0), at(7, 0)),
+        (at(7, 0), at(7, 1)),
+        (at(7, 0), at(8, 0)),
+        (at(8, 0), at(9, 0)),
+    ],
+    inputs: &[at(0, 0), at(0, 3)],
+    output: at(9, 0),
+};
+
+/// The layout of each gate kind, indexed by `GateKind as usize`.
+pub const GATE_LAYOUTS: [&GateLayout; 6] = [&AND, &OR, &NOT, &NAND, &NOR, &XOR];
+
+pub fn gate_layout(kind: GateKind) -> &'static GateLayout {
+    GATE_LAYOUTS[kind as usize]
+}
+
+/// The tiles of a gate of the given kind with its origin at `origin` that connect it to the rest
+/// of the circuit: its inputs, then its output.
+pub fn gate_pin_positions(kind: GateKind, origin: IVec2) -> Vec<(PinKind, IVec2)> {
+    let layout = gate_layout(kind);
+    layout
+        .inputs
+        .iter()
+        .map(|&input| (PinKind::Input, origin + input))
+        .chain([(PinKind::Output, origin + layout.output)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gate_layout, gate_pin_positions, pin_positions};
+    use crate::circuit::ComponentType;
+    use crate::direction::Direction;
+    use crate::logic_gate::GateKind;
+    use crate::rect::PinKind;
+    use glam::IVec2;
+
+    #[test]
+    fn flip_pin_positions() {
+        let pins = pin_positions(ComponentType::Flip, IVec2::new(2, 3), Direction::North);
+        assert_eq!(
+            pins,
+            [
+                (PinKind::Input, IVec2::new(3, 3)),
+                (PinKind::Input, IVec2::new(2, 2)),
+                (PinKind::Input, IVec2::new(1, 3)),
+                (PinKind::Output, IVec2::new(2, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn gate_pin_positions_follow_origin() {
+        assert_eq!(
+            gate_pin_positions(GateKind::Or, IVec2::new(10, -2)),
+            [
+                (PinKind::Input, IVec2::new(10, -2)),
+                (PinKind::Input, IVec2::new(10, 0)),
+                (PinKind::Output, IVec2::new(13, -2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn xor_crossover_does_not_connect() {
+        // The AND output runs along y = 1 across the NOR merge along x = 5.
+        let layout = gate_layout(GateKind::Xor);
+        let crossing = IVec2::new(5, 1);
+        let through = |(start, end): (IVec2, IVec2)| {
+            start != crossing
+                && end != crossing
+                && start.min(end).cmple(crossing).all()
+                && end.max(start).cmpge(crossing).all()
+        };
+        assert_eq!(
+            layout
+                .wires
+                .iter()
+                .copied()
+                .filter(|&wire| through(wire))
+                .count(),
+            2
+        );
+        assert!(!layout
+            .wires
+            .iter()
+            .any(|&(start, end)| start == crossing || end == crossing));
+    }
+
+    #[test]
+    fn gate_layouts_fit_their_size() {
+        for kind in GateKind::ALL {
+            let layout = gate_layout(kind);
+            let inside = |position: IVec2| {
+                position.cmpge(IVec2::ZERO).all() && position.cmplt(layout.size).all()
+            };
+            for &(start, end) in layout.wires {
+                assert!(inside(start) && inside(end), "{kind:?}");
+                assert!(start.x == end.x || start.y == end.y, "{kind:?}");
+            }
+            for &(_ty, position) in layout.components {
+                assert!(inside(position), "{kind:?}");
+            }
+            for &endpoint in layout.inputs.iter().chain([&layout.output]) {
+                assert!(
+                    layout
+                        .wires
+                        .iter()
+                        .any(|&(start, end)| start == endpoint || end == endpoint),
+                    "{kind:?}"
+                );
+            }
+            assert_eq!(layout.output.x, layout.size.x - 1, "{kind:?}");
+        }
+    }
+}
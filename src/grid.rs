@@ -0,0 +1,77 @@
+use crate::board::Board;
+use glam::{IVec2, Vec2};
+
+/// The snapping step used when snapping is turned on.
+pub const DEFAULT_STEP: IVec2 = IVec2::splat(2);
+
+/// Turns world positions into the tiles things are placed on, optionally snapped to a coarser
+/// grid.
+///
+/// A single aligner is shared by every placement mode, so that they all snap the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridAligner {
+    pub snap: bool,
+    /// The grid spacing in tiles, on each axis. Components smaller than one are treated as one.
+    pub step: IVec2,
+}
+
+impl Default for GridAligner {
+    fn default() -> Self {
+        Self {
+            snap: false,
+            step: DEFAULT_STEP,
+        }
+    }
+}
+
+impl GridAligner {
+    /// The tile containing `position`, moved to the nearest grid point if snapping.
+    pub fn align(&self, position: Vec2) -> IVec2 {
+        self.align_tile(position.floor().as_ivec2())
+    }
+
+    /// Moves `tile` to the nearest grid point if snapping.
+    pub fn align_tile(&self, tile: IVec2) -> IVec2 {
+        if self.snap {
+            round_to_multiple(tile, self.step.max(IVec2::ONE))
+        } else {
+            tile
+        }
+    }
+
+    /// Snaps the position and size of `board` if snapping, see [`Board::snap_to_grid`].
+    pub fn align_board(&self, board: &Board) -> Board {
+        if self.snap {
+            board.snap_to_grid(self.step)
+        } else {
+            *board
+        }
+    }
+}
+
+/// Rounds each axis of `value` to the nearest multiple of `grid`.
+pub fn round_to_multiple(value: IVec2, grid: IVec2) -> IVec2 {
+    (value.as_vec2() / grid.as_vec2()).round().as_ivec2() * grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GridAligner;
+    use glam::{IVec2, Vec2};
+
+    #[test]
+    fn aligns_to_tiles_and_grid() {
+        let mut grid = GridAligner {
+            snap: false,
+            step: IVec2::new(4, 2),
+        };
+        assert_eq!(grid.align(Vec2::new(5.7, -0.2)), IVec2::new(5, -1));
+
+        grid.snap = true;
+        assert_eq!(grid.align(Vec2::new(5.7, -0.2)), IVec2::new(4, -2));
+        assert_eq!(grid.align(Vec2::new(6.2, -3.5)), IVec2::new(8, -4));
+
+        grid.step = IVec2::ZERO;
+        assert_eq!(grid.align(Vec2::new(5.7, -0.2)), IVec2::new(5, -1));
+    }
+}
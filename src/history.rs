@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+/// How many edits can be undone by default.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 256;
+
+/// An edit of a `T` that can be undone and done again.
+pub trait Command<T> {
+    fn execute(&mut self, target: &mut T);
+    fn undo(&mut self, target: &mut T);
+}
+
+/// Undo and redo stacks of commands, e.g. [`crate::journal::CircuitEdit`]s.
+///
+/// Undoing a command moves it to the redo stack, and redoing moves it back. Once full, the oldest
+/// command is dropped.
+pub struct History<T> {
+    undo: VecDeque<Box<dyn Command<T>>>,
+    redo: Vec<Box<dyn Command<T>>>,
+    capacity: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Records a command that was just executed. Commands that were undone can no longer be
+    /// redone.
+    pub fn record(&mut self, command: Box<dyn Command<T>>) {
+        self.redo.clear();
+        self.undo.push_back(command);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Undoes the last command on `target`. Returns false if there is nothing to undo.
+    pub fn undo(&mut self, target: &mut T) -> bool {
+        match self.undo.pop_back() {
+            Some(mut command) => {
+                command.undo(target);
+                self.redo.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Executes the last undone command on `target` again. Returns false if there is nothing to
+    /// redo.
+    pub fn redo(&mut self, target: &mut T) -> bool {
+        match self.redo.pop() {
+            Some(mut command) => {
+                command.execute(target);
+                self.undo.push_back(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, History};
+
+    struct Add(i32);
+
+    impl Command<i32> for Add {
+        fn execute(&mut self, target: &mut i32) {
+            *target += self.0;
+        }
+
+        fn undo(&mut self, target: &mut i32) {
+            *target -= self.0;
+        }
+    }
+
+    fn execute(history: &mut History<i32>, state: &mut i32, amount: i32) {
+        let mut command = Add(amount);
+        command.execute(state);
+        history.record(Box::new(command));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(2);
+        let mut state = 0;
+        for amount in [1, 10, 100] {
+            execute(&mut history, &mut state, amount);
+        }
+
+        // Only the last two edits fit.
+        assert!(history.undo(&mut state));
+        assert_eq!(state, 11);
+        assert!(history.undo(&mut state));
+        assert_eq!(state, 1);
+        assert!(!history.undo(&mut state));
+
+        assert!(history.redo(&mut state));
+        assert_eq!(state, 11);
+
+        // A new edit drops what was left to redo.
+        execute(&mut history, &mut state, 1000);
+        assert!(!history.redo(&mut state));
+        assert!(history.undo(&mut state));
+        assert_eq!(state, 11);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::GraphicsContext;
 use bytemuck::Pod;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc;
 
@@ -8,13 +10,7 @@ pub struct InstanceManager<T> {
     gfx: GraphicsContext,
     buffer: Option<wgpu::Buffer>,
     buffer_capacity: usize,
-
-    update_tx: mpsc::Sender<Update<T>>,
-    update_rx: mpsc::Receiver<Update<T>>,
-    instances: Vec<T>,
-    instance_to_handle: Vec<u64>,
-    handle_to_instance: HashMap<u64, usize>,
-    buffer_update: bool,
+    instances: Instances<T>,
 }
 
 impl<T> InstanceManager<T>
@@ -22,34 +18,237 @@ where
     T: Pod,
 {
     pub fn new(gfx: &GraphicsContext) -> Self {
-        let (update_tx, update_rx) = mpsc::channel();
+        Self::with_capacity(gfx, 0)
+    }
 
-        Self {
+    /// Creates a manager with room for `capacity` instances, so that filling it does not
+    /// reallocate the GPU buffer.
+    pub fn with_capacity(gfx: &GraphicsContext, capacity: usize) -> Self {
+        let mut manager = Self {
             gfx: gfx.clone(),
             buffer: None,
             buffer_capacity: 0,
+            instances: Instances::with_capacity(capacity),
+        };
+        if capacity > 0 {
+            manager.ensure_capacity(capacity);
+        }
+        manager
+    }
+
+    /// Allocates room for at least `additional` more instances than currently present.
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.instances.reserve(additional);
+        if capacity > self.buffer_capacity {
+            self.ensure_capacity(capacity);
+            // The new buffer starts out empty.
+            self.instances.buffer_update = true;
+        }
+    }
+
+    /// Allocates room for at least `capacity` instances in total. Does nothing if there already
+    /// is, use [`Self::defragment`] to shrink the buffer.
+    pub fn grow_to(&mut self, capacity: usize) {
+        let len = self.instances.len();
+        self.reserve(capacity.saturating_sub(len));
+    }
+
+    pub fn insert(&mut self, instance: T) -> Handle<T> {
+        self.instances.insert(instance)
+    }
+
+    /// Exchanges the positions of two instances in the buffer, and so their draw order.
+    ///
+    /// Returns false if either handle is not (or no longer) part of this manager.
+    pub fn swap(&mut self, a: &Handle<T>, b: &Handle<T>) -> bool {
+        self.instances.swap(a.id, b.id)
+    }
+
+    /// The instance buffer, after uploading pending changes.
+    ///
+    /// Instances updated in place are written in as few contiguous ranges as possible; any other
+    /// change rewrites the whole buffer.
+    pub fn buffer(&mut self) -> Option<&wgpu::Buffer> {
+        self.instances.handle_updates();
+        if self.instances.buffer_update {
+            self.instances.buffer_update = false;
+            self.instances.dirty.clear();
+
+            self.ensure_capacity(self.instances.len());
+            if let Some(buffer) = &self.buffer {
+                let src_bytes: &[u8] = bytemuck::cast_slice(&self.instances.instances);
+                self.gfx.queue.write_buffer(buffer, 0, src_bytes);
+            }
+        } else if !self.instances.dirty.is_empty() {
+            if let Some(buffer) = &self.buffer {
+                self.instances.dirty.sort_unstable();
+                for range in contiguous_ranges(&self.instances.dirty) {
+                    let range = range.start as usize..range.end as usize;
+                    let offset = (range.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+                    let src_bytes: &[u8] = bytemuck::cast_slice(&self.instances.instances[range]);
+                    self.gfx.queue.write_buffer(buffer, offset, src_bytes);
+                }
+            }
+            self.instances.dirty.clear();
+        }
+        self.buffer.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Starts logging every instance added, replaced or removed, see [`Self::take_changes`].
+    pub fn track_changes(&mut self) {
+        self.instances.changes = Some(Vec::new());
+    }
+
+    /// The instances removed and added since the last call, in the order it happened, if
+    /// [`Self::track_changes`] was called. An instance that is set again counts as removing its
+    /// old value and adding the new one.
+    pub fn take_changes(&mut self) -> Vec<Change<T>> {
+        self.instances.handle_updates();
+        match &mut self.instances.changes {
+            Some(changes) => std::mem::take(changes),
+            None => Vec::new(),
+        }
+    }
+
+    /// The current instances, in buffer order.
+    pub fn instances(&mut self) -> &[T] {
+        self.instances.handle_updates();
+        &self.instances.instances
+    }
+
+    /// The positions in the instance buffer of the given handles, in ascending order.
+    ///
+    /// Handles that are not (or no longer) part of this manager are skipped.
+    pub fn indices<'a, I>(&mut self, handles: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = &'a Handle<T>>,
+        T: 'a,
+    {
+        self.indices_by_id(handles.into_iter().map(Handle::id))
+    }
+
+    /// Whether the handle identified by `id` still has an instance in this manager.
+    pub fn contains_id(&mut self, id: u64) -> bool {
+        self.instances.handle_updates();
+        self.instances.handle_to_instance.contains_key(&id)
+    }
+
+    /// Like [`Self::indices`], for handles identified by [`Handle::id`].
+    pub fn indices_by_id<I>(&mut self, ids: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        self.instances.indices_by_id(ids)
+    }
+
+    /// Releases unused GPU buffer space left behind by removed instances.
+    ///
+    /// Instances are kept packed by swap-removal, so there are no gaps to compact; this shrinks the
+    /// buffer to the smallest power of two that still fits the live instances.
+    pub fn defragment(&mut self) {
+        self.instances.handle_updates();
+        let new_cap = self.instances.len().checked_next_power_of_two().unwrap();
+        if new_cap < self.buffer_capacity {
+            self.buffer = None;
+            self.buffer_capacity = 0;
+            if self.instances.len() > 0 {
+                self.ensure_capacity(new_cap);
+            }
+            self.instances.buffer_update = true;
+        }
+    }
+
+    fn ensure_capacity(&mut self, cap: usize) {
+        if cap > self.buffer_capacity {
+            let new_cap = cap.checked_next_power_of_two().unwrap();
+            let bytes = std::mem::size_of::<T>() * new_cap;
+
+            let buffer = self.gfx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("{}.buffer", std::any::type_name::<Self>())),
+                size: bytes.try_into().unwrap(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            self.buffer = Some(buffer);
+            self.buffer_capacity = new_cap;
+        }
+    }
+}
+
+/// The instances of an [`InstanceManager`] and which handle owns each of them, kept apart from
+/// the GPU buffer they are uploaded to.
+struct Instances<T> {
+    update_tx: mpsc::Sender<Update<T>>,
+    update_rx: mpsc::Receiver<Update<T>>,
+    instances: Vec<T>,
+    instance_to_handle: Vec<u64>,
+    handle_to_instance: HashMap<u64, usize>,
+    // Set when instances were added, removed or moved, so the whole buffer must be written.
+    buffer_update: bool,
+    // Instances changed in place since the last write, uploaded alone unless `buffer_update`.
+    dirty: Vec<u32>,
+    changes: Option<Vec<Change<T>>>,
+}
+
+/// An instance removed from or added to an [`InstanceManager`], see
+/// [`InstanceManager::take_changes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<T> {
+    Removed(T),
+    Added(T),
+}
 
+impl<T: Copy> Instances<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        let (update_tx, update_rx) = mpsc::channel();
+        Self {
             update_tx,
             update_rx,
-            instances: Vec::new(),
-            instance_to_handle: Vec::new(),
-            handle_to_instance: HashMap::new(),
+            instances: Vec::with_capacity(capacity),
+            instance_to_handle: Vec::with_capacity(capacity),
+            handle_to_instance: HashMap::with_capacity(capacity),
             buffer_update: false,
+            dirty: Vec::new(),
+            changes: None,
         }
     }
 
-    pub fn insert(&mut self, instance: T) -> Handle<T> {
+    fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Applies pending handle updates and makes room for `additional` more instances. Returns
+    /// the number of instances there is room for.
+    fn reserve(&mut self, additional: usize) -> usize {
+        self.handle_updates();
+        self.instances.reserve(additional);
+        self.instance_to_handle.reserve(additional);
+        self.handle_to_instance.reserve(additional);
+        self.instances.len() + additional
+    }
+
+    fn insert(&mut self, instance: T) -> Handle<T> {
         let handle = Handle::new(self.update_tx.clone());
         handle.set(instance);
         handle
     }
 
     fn set(&mut self, handle: u64, instance: T) {
-        self.buffer_update = true;
-
+        if let Some(changes) = &mut self.changes {
+            if let Some(&index) = self.handle_to_instance.get(&handle) {
+                changes.push(Change::Removed(self.instances[index]));
+            }
+            changes.push(Change::Added(instance));
+        }
         if let Some(&index) = self.handle_to_instance.get(&handle) {
             self.instances[index] = instance;
+            self.dirty.push(index as u32);
         } else {
+            self.buffer_update = true;
             let index = self.instances.len();
             self.instances.push(instance);
             self.instance_to_handle.push(handle);
@@ -61,7 +260,10 @@ where
         self.buffer_update = true;
 
         let index = self.handle_to_instance.remove(&handle).unwrap();
-        self.instances.swap_remove(index);
+        let instance = self.instances.swap_remove(index);
+        if let Some(changes) = &mut self.changes {
+            changes.push(Change::Removed(instance));
+        }
 
         let removed_handle = self.instance_to_handle.swap_remove(index);
         debug_assert!(removed_handle == handle);
@@ -73,6 +275,25 @@ where
         }
     }
 
+    fn swap(&mut self, a: u64, b: u64) -> bool {
+        self.handle_updates();
+        let (index_a, index_b) = match (
+            self.handle_to_instance.get(&a),
+            self.handle_to_instance.get(&b),
+        ) {
+            (Some(&index_a), Some(&index_b)) => (index_a, index_b),
+            _ => return false,
+        };
+        if index_a != index_b {
+            self.buffer_update = true;
+            self.instances.swap(index_a, index_b);
+            self.instance_to_handle.swap(index_a, index_b);
+            self.handle_to_instance.insert(a, index_b);
+            self.handle_to_instance.insert(b, index_a);
+        }
+        true
+    }
+
     fn handle_updates(&mut self) {
         while let Ok(update) = self.update_rx.try_recv() {
             match update {
@@ -82,39 +303,33 @@ where
         }
     }
 
-    pub fn buffer(&mut self) -> Option<&wgpu::Buffer> {
+    fn indices_by_id<I>(&mut self, ids: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = u64>,
+    {
         self.handle_updates();
-        if self.buffer_update {
-            self.buffer_update = false;
-
-            self.ensure_capacity(self.instances.len());
-            if let Some(buffer) = &self.buffer {
-                let src_bytes: &[u8] = bytemuck::cast_slice(&self.instances);
-                self.gfx.queue.write_buffer(buffer, 0, src_bytes);
-            }
-        }
-        self.buffer.as_ref()
-    }
-
-    pub fn len(&self) -> usize {
-        self.instances.len()
+        let mut indices: Vec<u32> = ids
+            .into_iter()
+            .filter_map(|id| self.handle_to_instance.get(&id))
+            .map(|&index| index.try_into().expect("too many instances"))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
     }
+}
 
-    fn ensure_capacity(&mut self, cap: usize) {
-        if cap > self.buffer_capacity {
-            let new_cap = cap.checked_next_power_of_two().unwrap();
-            let bytes = std::mem::size_of::<T>() * new_cap;
-
-            let buffer = self.gfx.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(&format!("{}.buffer", std::any::type_name::<Self>())),
-                size: bytes.try_into().unwrap(),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            });
-            self.buffer = Some(buffer);
-            self.buffer_capacity = new_cap;
+/// Groups sorted instance indices into as few ranges as possible, e.g. to minimize draw calls or
+/// buffer writes. Repeated indices are merged.
+pub fn contiguous_ranges(indices: &[u32]) -> Vec<Range<u32>> {
+    let mut ranges: Vec<Range<u32>> = Vec::new();
+    for &index in indices {
+        match ranges.last_mut() {
+            Some(range) if index <= range.end => range.end = range.end.max(index + 1),
+            _ => ranges.push(index..index + 1),
         }
     }
+    ranges
 }
 
 enum Update<T> {
@@ -139,9 +354,34 @@ impl<T> Handle<T> {
         Self { id, updates }
     }
 
+    /// A number identifying this handle, unique for the lifetime of the program.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
     pub fn set(&self, instance: T) {
         self.updates.send(Update::Set(self.id, instance)).ok();
     }
+
+    /// A handle not attached to any manager, whose updates go nowhere.
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self::new(mpsc::channel().0)
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
 }
 
 impl<T> Drop for Handle<T> {
@@ -149,3 +389,84 @@ impl<T> Drop for Handle<T> {
         self.updates.send(Update::Remove(self.id)).ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{contiguous_ranges, Change, Instances};
+
+    #[test]
+    fn contiguous_ranges_merge_adjacent_indices() {
+        assert_eq!(contiguous_ranges(&[]), vec![]);
+        assert_eq!(
+            contiguous_ranges(&[0, 2, 3, 3, 7, 8, 9]),
+            vec![0..1, 2..4, 7..10]
+        );
+    }
+
+    #[test]
+    fn handles_survive_growing() {
+        let mut instances = Instances::with_capacity(2);
+        let mut handles: Vec<_> = (0..3u32).map(|value| instances.insert(value)).collect();
+        assert_eq!(instances.reserve(100), 103);
+
+        // Handles created before growing can still update and remove their instances.
+        handles[2].set(20);
+        drop(handles.remove(0));
+        handles.push(instances.insert(3));
+        instances.handle_updates();
+
+        assert_eq!(instances.len(), 3);
+        for (handle, expected) in handles.iter().zip([1, 20, 3]) {
+            let index = instances.indices_by_id([handle.id()]);
+            assert_eq!(instances.instances[index[0] as usize], expected);
+        }
+    }
+
+    #[test]
+    fn tracks_changes() {
+        let mut instances = Instances::with_capacity(0);
+        let first = instances.insert(1u32);
+        instances.handle_updates();
+        instances.changes = Some(Vec::new());
+        let second = instances.insert(2);
+        first.set(3);
+        drop(second);
+        instances.handle_updates();
+
+        assert_eq!(
+            instances.changes.take().unwrap(),
+            vec![
+                Change::Added(2),
+                Change::Removed(1),
+                Change::Added(3),
+                Change::Removed(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn swap_exchanges_slots() {
+        let mut instances = Instances::with_capacity(0);
+        let first = instances.insert(1u32);
+        let second = instances.insert(2);
+        let third = instances.insert(3);
+        instances.handle_updates();
+        instances.buffer_update = false;
+
+        assert!(instances.swap(first.id(), third.id()));
+        assert_eq!(instances.instances, vec![3, 2, 1]);
+        assert_eq!(instances.indices_by_id([first.id()]), vec![2]);
+        assert_eq!(instances.indices_by_id([third.id()]), vec![0]);
+        assert_eq!(instances.indices_by_id([second.id()]), vec![1]);
+        // The moved instances are only uploaded with the whole buffer.
+        assert!(instances.buffer_update);
+
+        // The handles still update their own instances.
+        first.set(10);
+        instances.handle_updates();
+        assert_eq!(instances.instances, vec![3, 2, 10]);
+
+        let detached = super::Handle::<u32>::detached();
+        assert!(!instances.swap(first.id(), detached.id()));
+    }
+}
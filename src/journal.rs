@@ -0,0 +1,237 @@
+use crate::board::Board;
+use crate::history::Command;
+use crate::rect::PinKind;
+use crate::serializer::{ComponentRecord, WireRecord};
+use glam::IVec2;
+
+/// A change recorded while [`crate::circuit::Circuit::start_journal`] is active.
+///
+/// Components and wires are identified by position rather than by handle, since undoing a
+/// removal puts back a new object under a new handle.
+pub enum Change {
+    AddComponent {
+        record: ComponentRecord,
+        /// Names of the component's input and output nets, unused when it is first added.
+        net_names: [Option<String>; 2],
+    },
+    RemoveComponent {
+        record: ComponentRecord,
+        net_names: [Option<String>; 2],
+    },
+    AddWire {
+        record: WireRecord,
+    },
+    RemoveWire {
+        record: WireRecord,
+        net_name: Option<String>,
+    },
+    SetPinKind {
+        position: IVec2,
+        from: PinKind,
+        to: PinKind,
+    },
+    ToggleSwitch {
+        position: IVec2,
+    },
+    AddBoard {
+        index: usize,
+        board: Board,
+    },
+    RemoveBoard {
+        index: usize,
+        board: Board,
+    },
+    UpdateBoard {
+        index: usize,
+        from: Board,
+        to: Board,
+    },
+}
+
+/// Something the recorded [`Change`]s can be applied to, i.e. a circuit.
+pub trait EditTarget {
+    /// Inserts the component described by `record`, switched and with its pin kind, and gives
+    /// its nets back their names unless they already have one.
+    fn restore_component(&mut self, record: &ComponentRecord, net_names: &[Option<String>; 2]);
+    fn remove_component_at(&mut self, position: IVec2);
+    fn restore_wire(&mut self, record: WireRecord, net_name: Option<&str>);
+    fn remove_wire_between(&mut self, record: WireRecord);
+    fn set_pin_kind_at(&mut self, position: IVec2, kind: PinKind);
+    fn toggle_switch_at(&mut self, position: IVec2);
+    fn insert_board_at(&mut self, index: usize, board: Board);
+    fn remove_board_at(&mut self, index: usize);
+    fn set_board_at(&mut self, index: usize, board: Board);
+
+    /// Applies `changes` in order, or undoes them in reverse if `undo` is set.
+    fn apply_changes(&mut self, changes: &[Change], undo: bool) {
+        apply_changes(self, changes, undo);
+    }
+}
+
+/// The default [`EditTarget::apply_changes`].
+pub fn apply_changes<T: EditTarget + ?Sized>(target: &mut T, changes: &[Change], undo: bool) {
+    if undo {
+        for change in changes.iter().rev() {
+            apply_change(target, change, undo);
+        }
+    } else {
+        for change in changes {
+            apply_change(target, change, undo);
+        }
+    }
+}
+
+fn apply_change<T: EditTarget + ?Sized>(target: &mut T, change: &Change, undo: bool) {
+    match (change, undo) {
+        (Change::AddComponent { record, net_names }, false)
+        | (Change::RemoveComponent { record, net_names }, true) => {
+            target.restore_component(record, net_names);
+        }
+        (Change::AddComponent { record, .. }, true)
+        | (Change::RemoveComponent { record, .. }, false) => {
+            target.remove_component_at(record.position);
+        }
+        (Change::AddWire { record }, false) => target.restore_wire(*record, None),
+        (Change::RemoveWire { record, net_name }, true) => {
+            target.restore_wire(*record, net_name.as_deref());
+        }
+        (Change::AddWire { record }, true) | (Change::RemoveWire { record, .. }, false) => {
+            target.remove_wire_between(*record);
+        }
+        (Change::SetPinKind { position, from, to }, undo) => {
+            target.set_pin_kind_at(*position, if undo { *from } else { *to });
+        }
+        (Change::ToggleSwitch { position }, _) => target.toggle_switch_at(*position),
+        (Change::AddBoard { index, board }, false)
+        | (Change::RemoveBoard { index, board }, true) => {
+            target.insert_board_at(*index, *board);
+        }
+        (Change::AddBoard { index, .. }, true) | (Change::RemoveBoard { index, .. }, false) => {
+            target.remove_board_at(*index);
+        }
+        (Change::UpdateBoard { index, from, to }, undo) => {
+            target.set_board_at(*index, if undo { *from } else { *to });
+        }
+    }
+}
+
+/// An undoable edit of a circuit, made of the changes recorded by
+/// [`crate::circuit::Circuit::take_journal`].
+///
+/// Undoing and redoing only touch the components, wires and boards that were changed.
+pub struct CircuitEdit {
+    changes: Vec<Change>,
+}
+
+impl CircuitEdit {
+    pub fn new(changes: Vec<Change>) -> Self {
+        Self { changes }
+    }
+}
+
+impl<T: EditTarget> Command<T> for CircuitEdit {
+    fn execute(&mut self, target: &mut T) {
+        target.apply_changes(&self.changes, false);
+    }
+
+    fn undo(&mut self, target: &mut T) {
+        target.apply_changes(&self.changes, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, CircuitEdit, EditTarget};
+    use crate::board::Board;
+    use crate::circuit::ComponentType;
+    use crate::direction::Direction;
+    use crate::history::History;
+    use crate::rect::PinKind;
+    use crate::serializer::{ComponentRecord, WireRecord};
+    use glam::IVec2;
+    use std::collections::HashMap;
+
+    /// Components by position, panicking on changes that do not match the current state.
+    #[derive(Default)]
+    struct Layout {
+        components: HashMap<IVec2, ComponentRecord>,
+        journal: Vec<Change>,
+    }
+
+    impl Layout {
+        fn place_pin(&mut self, position: IVec2) {
+            let record = ComponentRecord {
+                ty: ComponentType::Pin,
+                position,
+                orientation: Direction::East,
+                switched: false,
+                pin_kind: PinKind::default(),
+            };
+            self.restore_component(&record, &Default::default());
+            self.journal.push(Change::AddComponent {
+                record,
+                net_names: Default::default(),
+            });
+        }
+
+        fn delete(&mut self, position: IVec2) {
+            let record = self.components[&position];
+            self.remove_component_at(position);
+            self.journal.push(Change::RemoveComponent {
+                record,
+                net_names: Default::default(),
+            });
+        }
+
+        fn take_edit(&mut self) -> Box<CircuitEdit> {
+            Box::new(CircuitEdit::new(std::mem::take(&mut self.journal)))
+        }
+    }
+
+    impl EditTarget for Layout {
+        fn restore_component(&mut self, record: &ComponentRecord, _: &[Option<String>; 2]) {
+            assert!(self.components.insert(record.position, *record).is_none());
+        }
+
+        fn remove_component_at(&mut self, position: IVec2) {
+            self.components.remove(&position).unwrap();
+        }
+
+        fn restore_wire(&mut self, _: WireRecord, _: Option<&str>) {}
+
+        fn remove_wire_between(&mut self, _: WireRecord) {}
+
+        fn set_pin_kind_at(&mut self, _: IVec2, _: PinKind) {}
+
+        fn toggle_switch_at(&mut self, _: IVec2) {}
+
+        fn insert_board_at(&mut self, _: usize, _: Board) {}
+
+        fn remove_board_at(&mut self, _: usize) {}
+
+        fn set_board_at(&mut self, _: usize, _: Board) {}
+    }
+
+    #[test]
+    fn undo_redo_recreated_component() {
+        let position = IVec2::new(1, 2);
+        let mut layout = Layout::default();
+        let mut history = History::default();
+
+        layout.place_pin(position);
+        history.record(layout.take_edit());
+        layout.delete(position);
+        history.record(layout.take_edit());
+
+        assert!(history.undo(&mut layout));
+        assert!(layout.components.contains_key(&position));
+        // The pin put back by the first undo is the one the second undo removes.
+        assert!(history.undo(&mut layout));
+        assert!(layout.components.is_empty());
+
+        assert!(history.redo(&mut layout));
+        assert!(layout.components.contains_key(&position));
+        assert!(history.redo(&mut layout));
+        assert!(layout.components.is_empty());
+    }
+}
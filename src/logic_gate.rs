@@ -0,0 +1,182 @@
+use crate::gate_layout;
+use crate::rect::PinKind;
+use glam::IVec2;
+
+/// A logic gate that [`crate::circuit::Circuit::place_gate`] builds out of flips and flops.
+///
+/// The order of the variants indexes [`gate_layout::GATE_LAYOUTS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    And,
+    Or,
+    Not,
+    Nand,
+    Nor,
+    Xor,
+}
+
+impl GateKind {
+    pub const ALL: [GateKind; 6] = [
+        GateKind::And,
+        GateKind::Or,
+        GateKind::Not,
+        GateKind::Nand,
+        GateKind::Nor,
+        GateKind::Xor,
+    ];
+
+    /// The color of the board placed under the gate, to tell the kinds apart.
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            GateKind::And => [0.25, 0.35, 0.6, 1.0],
+            GateKind::Or => [0.25, 0.55, 0.3, 1.0],
+            GateKind::Not => [0.6, 0.3, 0.25, 1.0],
+            GateKind::Nand => [0.45, 0.3, 0.6, 1.0],
+            GateKind::Nor => [0.6, 0.55, 0.25, 1.0],
+            GateKind::Xor => [0.25, 0.55, 0.6, 1.0],
+        }
+    }
+}
+
+/// A gate placed in a circuit, in tile positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub inputs: Vec<IVec2>,
+    pub output: IVec2,
+}
+
+impl Gate {
+    pub fn new(kind: GateKind, origin: IVec2) -> Self {
+        let mut inputs = Vec::new();
+        let mut output = origin;
+        for (pin_kind, position) in gate_layout::gate_pin_positions(kind, origin) {
+            match pin_kind {
+                PinKind::Output => output = position,
+                _ => inputs.push(position),
+            }
+        }
+        Self {
+            kind,
+            inputs,
+            output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GateKind;
+    use crate::circuit::ComponentType;
+    use crate::gate_layout::{self, GateLayout};
+    use crate::simulation::Simulation;
+    use glam::IVec2;
+    use std::collections::HashMap;
+
+    /// Where a wire end connects: a pin joining the wires ending on a free tile, or the input or
+    /// output side of the component on the tile.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    enum Node {
+        Pin(IVec2),
+        Input(IVec2),
+        Output(IVec2),
+    }
+
+    /// Builds the flips and flops of `layout` in a simulation, with a cluster per net like the
+    /// circuit would allocate. Wires only connect at their ends, so crossing wires stay apart.
+    /// Returns the clusters of the inputs and of the output.
+    fn build(layout: &GateLayout, simulation: &mut Simulation) -> (Vec<u32>, u32) {
+        let components: HashMap<IVec2, ComponentType> = layout
+            .components
+            .iter()
+            .map(|&(ty, position)| (position, ty))
+            .collect();
+        // Components face east: the east side is the output, every other side the input.
+        let node = |end: IVec2, other: IVec2| {
+            if !components.contains_key(&end) {
+                Node::Pin(end)
+            } else if (other - end).signum() == IVec2::X {
+                Node::Output(end)
+            } else {
+                Node::Input(end)
+            }
+        };
+
+        let mut clusters: HashMap<Node, u32> = HashMap::new();
+        for &(start, end) in layout.wires {
+            let (a, b) = (node(start, end), node(end, start));
+            match (clusters.get(&a).copied(), clusters.get(&b).copied()) {
+                (Some(x), Some(y)) if x != y => {
+                    for cluster in clusters.values_mut() {
+                        if *cluster == y {
+                            *cluster = x;
+                        }
+                    }
+                }
+                (Some(x), None) => {
+                    clusters.insert(b, x);
+                }
+                (None, Some(y)) => {
+                    clusters.insert(a, y);
+                }
+                (None, None) => {
+                    let cluster = simulation.alloc_cluster();
+                    clusters.insert(a, cluster);
+                    clusters.insert(b, cluster);
+                }
+                _ => {}
+            }
+        }
+
+        for (&position, &ty) in &components {
+            let input = clusters[&Node::Input(position)];
+            let output = clusters[&Node::Output(position)];
+            match ty {
+                ComponentType::Flip => simulation.add_flip(input, output),
+                ComponentType::Flop => simulation.add_flop(input, output),
+                _ => unreachable!(),
+            }
+        }
+        let inputs = layout
+            .inputs
+            .iter()
+            .map(|&input| clusters[&Node::Pin(input)])
+            .collect();
+        (inputs, clusters[&Node::Pin(layout.output)])
+    }
+
+    #[test]
+    fn gate_truth_tables() {
+        for kind in GateKind::ALL {
+            let expected: fn(bool, bool) -> bool = match kind {
+                GateKind::And => |a, b| a && b,
+                GateKind::Or => |a, b| a || b,
+                GateKind::Not => |a, _| !a,
+                GateKind::Nand => |a, b| !(a && b),
+                GateKind::Nor => |a, b| !(a || b),
+                GateKind::Xor => |a, b| a != b,
+            };
+            let layout = gate_layout::gate_layout(kind);
+            for pattern in 0..1u32 << layout.inputs.len() {
+                let mut simulation = Simulation::new();
+                let (inputs, output) = build(layout, &mut simulation);
+                for (bit, &input) in inputs.iter().enumerate() {
+                    if pattern >> bit & 1 != 0 {
+                        simulation.power(input);
+                    }
+                }
+                let mut ticks = 0;
+                while simulation.tick() != 0 {
+                    ticks += 1;
+                    assert!(ticks < 100, "{kind:?} does not settle");
+                }
+                let (a, b) = (pattern & 1 != 0, pattern & 2 != 0);
+                assert_eq!(
+                    simulation.is_powered(output),
+                    expected(a, b),
+                    "{kind:?}({a}, {b})"
+                );
+            }
+        }
+    }
+}
@@ -1,30 +1,65 @@
 pub mod board;
 pub mod circuit;
+pub mod clipboard;
+pub mod color_utils;
 pub mod counter;
 pub mod cursor;
 pub mod depot;
 pub mod direction;
+pub mod fps_limiter;
+pub mod frame_timing;
+pub mod gamepad;
+pub mod gate_layout;
+pub mod grid;
+pub mod history;
 pub mod instance;
+pub mod journal;
+pub mod logic_gate;
+pub mod power_bar;
+pub mod profiler;
+pub mod recorder;
 pub mod rect;
+pub mod screen_bars;
 pub mod screen_vertex;
+pub mod selection;
+pub mod serializer;
 pub mod simulation;
+pub mod synthesis;
+pub mod theme;
+pub mod tooltip;
 pub mod viewport;
 
-use crate::circuit::Circuit;
 use crate::circuit::ComponentType;
+use crate::circuit::{Circuit, DebugMarkerKind};
+use crate::clipboard::Clipboard;
 use crate::counter::Counter;
 use crate::cursor::{CursorManager, CursorState};
 use crate::direction::Direction;
-use crate::viewport::Viewport;
+use crate::fps_limiter::FpsLimiter;
+use crate::frame_timing::FrameTimingGraph;
+use crate::gamepad::Gamepad;
+use crate::grid::GridAligner;
+use crate::history::History;
+use crate::logic_gate::GateKind;
+use crate::power_bar::PowerBar;
+use crate::recorder::{CircuitCommand, MacroRecorder};
+use crate::selection::Selection;
+use crate::serializer::{CircuitSerializer, RectI};
+use crate::theme::Theme;
+use crate::tooltip::Tooltip;
+use crate::viewport::{Camera, Viewport, FAST_PAN_FACTOR, SLOW_PAN_FACTOR};
 use anyhow::Context;
 use futures_executor::block_on;
-use glam::Vec2;
+use glam::{IVec2, Vec2};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use wgpu_glyph::ab_glyph::FontArc;
-use wgpu_glyph::{GlyphBrushBuilder, Section, Text};
+use wgpu_glyph::{
+    GlyphBrushBuilder, GlyphCruncher, HorizontalAlign, Layout, Section, Text, VerticalAlign,
+};
 use winit::event::{
-    ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    DeviceEvent, DeviceId, ElementState, Event, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
 };
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{CursorIcon, Window, WindowBuilder};
@@ -32,19 +67,60 @@ use winit::window::{CursorIcon, Window, WindowBuilder};
 const HELP_TEXT: &str = "\
 Controls (press F1 to show/hide):
 Camera Pan - WASD or arrow keys
+    (Shift for fast, Ctrl for slow)
     or middle click and drag
 Camera Zoom - Scroll or PgUp/PgDn
+    or gamepad sticks
+Camera Orbit - Ctrl+Alt+Left click and drag
 Place Component - Left click
 Place Wire - Left click and drag
-Remove Component/Wire - Right click
+Remove Component/Wire/Board - Right click
+Measure Distance - Hold Shift+M, left click and drag
+Remove Entire Net - Alt+Right click
+Erase - Ctrl+Right click and drag
+Remove Unconnected Pins - Ctrl+Shift+P
+Merge Straight Wires - Ctrl+M
 Rotate Component - R
 Interact with Component - E
+Cycle Pin Kind - K
+Follow Component - F
+Cycle Theme - Ctrl+T
+Toggle Grid Snap - Ctrl+G
+Select Area - Shift+Left click and drag
+Move Selection - Left click and drag inside it
+Delete Selection - Delete
+Copy Selection or Board Contents - Ctrl+C
+Paste at Cursor - Ctrl+V
+Undo - Ctrl+Z
+Redo - Ctrl+Y or Ctrl+Shift+Z
+Show Circuit Problems - F2
+Clock Net Display (dashed wires) - L
+Frame Timing Graph - F3
+Wireframe (if supported) - Alt+W
+Print Profiler Report - F4
+Record Macro - F7 start, F8 stop
+Play Macro at Cursor - F9
+Save Circuit - F5, Load - F6
 1 - Pin/Wire
 2 - Flip
 3 - Flop
 4 - Switch
+Place Gate at Cursor - Q, Cycle Gate - Shift+Q
 ";
 
+/// Deleting a net with more elements than this asks for a second Alt+right click.
+const FLOOD_DELETE_CONFIRM_THRESHOLD: usize = 20;
+
+/// The opacity of the circuit while circuit problems are shown.
+const DEBUG_MARKERS_CIRCUIT_ALPHA: f32 = 0.4;
+
+const THEME_TRANSITION_DURATION: Duration = Duration::from_millis(300);
+
+const CUSTOM_THEME_PATH: &str = "theme.toml";
+const MACRO_PATH: &str = "macro.json";
+const CIRCUIT_PATH: &str = "circuit.flipflop";
+const CAMERA_SETTINGS_PATH: &str = "camera.toml";
+
 pub type GraphicsContext = Arc<GraphicsContextInner>;
 
 pub struct GraphicsContextInner {
@@ -58,22 +134,46 @@ pub struct GraphicsContextInner {
 }
 
 impl GraphicsContextInner {
-    async fn new(window: Window) -> anyhow::Result<Self> {
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
+    async fn new(window: Window, software_render: bool) -> anyhow::Result<Self> {
+        let backends = if software_render {
+            wgpu::util::backend_bits_from_env()
+                .unwrap_or(wgpu::Backends::GL | wgpu::Backends::VULKAN)
+        } else {
+            wgpu::Backends::all()
+        };
+        let instance = wgpu::Instance::new(backends);
         let surface = unsafe { instance.create_surface(&window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                compatible_surface: Some(&surface),
-                power_preference: wgpu::PowerPreference::LowPower,
-                ..Default::default()
-            })
-            .await
-            .context("Failed to find a suitable adapter")?;
+        let adapter = if software_render {
+            // Honor WGPU_ADAPTER_NAME (e.g. "llvmpipe") if set, otherwise ask for the fallback
+            // adapter, which is a software rasterizer on platforms that provide one.
+            match wgpu::util::initialize_adapter_from_env(&instance, backends) {
+                Some(adapter) => Some(adapter),
+                None => {
+                    instance
+                        .request_adapter(&wgpu::RequestAdapterOptions {
+                            compatible_surface: Some(&surface),
+                            force_fallback_adapter: true,
+                            ..Default::default()
+                        })
+                        .await
+                }
+            }
+        } else {
+            instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    compatible_surface: Some(&surface),
+                    power_preference: wgpu::PowerPreference::LowPower,
+                    ..Default::default()
+                })
+                .await
+        }
+        .context("Failed to find a suitable adapter")?;
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: Default::default(),
+                    // Optional, for the wireframe debug view.
+                    features: adapter.features() & wgpu::Features::POLYGON_MODE_LINE,
                     limits: Default::default(),
                 },
                 None,
@@ -118,11 +218,46 @@ struct State {
     staging_belt: wgpu::util::StagingBelt,
     viewport: Viewport,
     frame_counter: Counter,
+    frame_timing_graph: FrameTimingGraph,
+    power_bar: PowerBar,
+    fps_limiter: FpsLimiter,
     should_close: bool,
     last_update: Instant,
     circuit: Circuit,
     cursor_manager: CursorManager,
     draw_help: bool,
+    show_debug_markers: bool,
+    wireframe: bool,
+    debug_markers: Vec<(IVec2, DebugMarkerKind)>,
+    hovering_component: bool,
+    /// The kind of gate placed with Q.
+    gate_kind: GateKind,
+    tooltip: Tooltip,
+    last_cursor_position: Vec2,
+    start_time: Instant,
+    modifiers: ModifiersState,
+    /// Whether Shift+M is held, so left dragging measures instead of placing.
+    measure_key: bool,
+    themes: Vec<Theme>,
+    theme_index: usize,
+    /// The theme currently shown, which differs from the selected one during a transition.
+    displayed_theme: Theme,
+    theme_transition: Option<(Theme, Instant)>,
+    macro_recorder: MacroRecorder,
+    clipboard: Clipboard,
+    /// The tiles selected with Shift+drag.
+    selection: Selection,
+    gamepad: Gamepad,
+    flood_delete_confirm_threshold: usize,
+    pending_flood_delete: Option<(IVec2, usize)>,
+    /// The edits made to the circuit, see [`Self::edit`].
+    history: History<Circuit>,
+    /// Whether closing was requested with unsaved changes; a second request closes anyway.
+    pending_close: bool,
+    /// Aligns everything placed with the cursor.
+    grid: GridAligner,
+    /// Whether the window title currently marks unsaved changes.
+    title_unsaved: bool,
 }
 
 fn create_depth_texture(gfx: &GraphicsContext) -> wgpu::Texture {
@@ -143,7 +278,7 @@ fn create_depth_texture(gfx: &GraphicsContext) -> wgpu::Texture {
 
 impl State {
     async fn new(window: Window) -> anyhow::Result<Self> {
-        let gfx = Arc::new(GraphicsContextInner::new(window).await?);
+        let gfx = Arc::new(GraphicsContextInner::new(window, software_render_requested()).await?);
         gfx.reconfigure();
         let depth_texture = create_depth_texture(&gfx);
         let depth_texture_view = depth_texture.create_view(&Default::default());
@@ -153,10 +288,54 @@ impl State {
             GlyphBrushBuilder::using_font(fira_sans).build(&gfx.device, gfx.render_format);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
-        let viewport = Viewport::new(&gfx);
+        let mut viewport = Viewport::new(&gfx);
+        // Camera settings in the working directory, such as the pan speed, apply at startup.
+        if std::path::Path::new(CAMERA_SETTINGS_PATH).exists() {
+            match Camera::load_from_file(CAMERA_SETTINGS_PATH) {
+                Ok(camera) => {
+                    let pan_speed = camera.pan_speed;
+                    *viewport.camera_mut() = camera;
+                    // Clamps a negative pan speed from the file.
+                    viewport.set_pan_speed(pan_speed);
+                }
+                Err(err) => eprintln!("{:?}", err),
+            }
+        }
 
-        let circuit = Circuit::new(&gfx, &viewport);
+        let mut circuit = Circuit::new(&gfx, &viewport);
         let cursor_manager = CursorManager::new(&gfx, &viewport);
+        let frame_timing_graph = FrameTimingGraph::new(&gfx, &viewport);
+        let power_bar = PowerBar::new(&gfx, &viewport);
+
+        // A theme file in the working directory overrides the builtin theme of the same name,
+        // and is applied at startup.
+        let custom_theme = if std::path::Path::new(CUSTOM_THEME_PATH).exists() {
+            Theme::load_from_file(CUSTOM_THEME_PATH)
+                .map_err(|err| eprintln!("{:?}", err))
+                .ok()
+        } else {
+            None
+        };
+        let initial_theme = custom_theme
+            .as_ref()
+            .map(|theme| theme.name.clone())
+            .unwrap_or_else(|| Theme::list_builtin()[0].to_owned());
+        let themes = Theme::all_with(custom_theme);
+        let theme_index = themes
+            .iter()
+            .position(|theme| theme.name == initial_theme)
+            .unwrap();
+        circuit.apply_theme(&themes[theme_index]);
+
+        // Restore the last saved macro, so it can be played back across sessions.
+        let macro_recorder = if std::path::Path::new(MACRO_PATH).exists() {
+            MacroRecorder::load(MACRO_PATH).unwrap_or_else(|err| {
+                eprintln!("{:?}", err);
+                MacroRecorder::new()
+            })
+        } else {
+            MacroRecorder::new()
+        };
 
         Ok(Self {
             gfx,
@@ -166,22 +345,77 @@ impl State {
             staging_belt,
             viewport,
             frame_counter: Counter::new(),
+            frame_timing_graph,
+            power_bar,
+            fps_limiter: FpsLimiter::new(),
             should_close: false,
             last_update: Instant::now(),
             circuit,
             cursor_manager,
             draw_help: true,
+            show_debug_markers: false,
+            wireframe: false,
+            debug_markers: Vec::new(),
+            hovering_component: false,
+            gate_kind: GateKind::And,
+            tooltip: Tooltip::new(),
+            last_cursor_position: Vec2::ZERO,
+            start_time: Instant::now(),
+            modifiers: ModifiersState::empty(),
+            measure_key: false,
+            displayed_theme: themes[theme_index].clone(),
+            themes,
+            theme_index,
+            theme_transition: None,
+            macro_recorder,
+            clipboard: Clipboard::new(),
+            selection: Selection::new(),
+            gamepad: Gamepad::new(),
+            flood_delete_confirm_threshold: FLOOD_DELETE_CONFIRM_THRESHOLD,
+            pending_flood_delete: None,
+            history: History::default(),
+            pending_close: false,
+            grid: GridAligner::default(),
+            title_unsaved: false,
         })
     }
 
+    fn handle_device_event(&mut self, device_id: DeviceId, event: DeviceEvent) {
+        self.gamepad.handle_device_event(device_id, &event);
+        let pan = self.gamepad.pan();
+        let zoom = self.gamepad.zoom();
+        if pan != self.viewport.camera().analog_pan || zoom != self.viewport.camera().analog_zoom {
+            let mut camera = self.viewport.camera_mut();
+            camera.analog_pan = pan;
+            camera.analog_zoom = zoom;
+        }
+    }
+
     fn handle_window_event(&mut self, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
-                self.should_close = true;
+                if self.circuit.has_unsaved_changes() && !self.pending_close {
+                    self.pending_close = true;
+                } else {
+                    self.should_close = true;
+                }
             }
             WindowEvent::Resized(..) | WindowEvent::ScaleFactorChanged { .. } => {
                 self.reconfigure();
             }
+            WindowEvent::Focused(focused) => {
+                self.fps_limiter.set_focused(focused);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                self.viewport.camera_mut().pan_speed_factor = if modifiers.shift() {
+                    FAST_PAN_FACTOR
+                } else if modifiers.ctrl() {
+                    SLOW_PAN_FACTOR
+                } else {
+                    1.0
+                };
+            }
             WindowEvent::CursorMoved { position, .. } => {
                 let position = Vec2::new(position.x as f32, position.y as f32);
                 self.viewport.cursor_moved(position);
@@ -195,22 +429,44 @@ impl State {
                     match self.cursor_manager.current_state() {
                         CursorState::Pan { .. } => {
                             self.cursor_manager.end();
-                            self.gfx.window.set_cursor_icon(CursorIcon::Default);
+                            self.gfx.window.set_cursor_icon(self.hover_icon());
                         }
                         _ => {}
                     }
                 }
+                (MouseButton::Left, ElementState::Pressed)
+                    if self.modifiers.ctrl() && self.modifiers.alt() =>
+                {
+                    self.cursor_manager.start_orbit(&self.viewport);
+                    self.gfx.window.set_cursor_icon(CursorIcon::Grabbing);
+                }
+                (MouseButton::Left, ElementState::Pressed) if self.measure_key => {
+                    self.cursor_manager.start_measure(&self.viewport);
+                }
+                (MouseButton::Left, ElementState::Pressed) if self.modifiers.shift() => {
+                    self.cursor_manager.start_select(&self.viewport);
+                }
+                (MouseButton::Left, ElementState::Pressed)
+                    if self.selection.contains(self.viewport.cursor().tile()) =>
+                {
+                    if let Some(bounds) = self.selection.bounds() {
+                        self.cursor_manager
+                            .start_move_selection(&self.viewport, bounds);
+                    }
+                }
                 (MouseButton::Left, ElementState::Pressed) => {
+                    self.set_selection(Selection::new());
                     match self.cursor_manager.place_type() {
                         ComponentType::Pin => {
-                            self.cursor_manager.start_place_wire(&self.viewport);
+                            self.cursor_manager
+                                .start_place_wire(&self.viewport, &self.grid);
                         }
                         other_type => {
-                            self.circuit.place_component(
-                                other_type,
-                                self.viewport.cursor().tile(),
-                                self.cursor_manager.place_orientation(),
-                            );
+                            self.execute(CircuitCommand::PlaceComponent {
+                                ty: other_type,
+                                position: self.grid.align(self.viewport.cursor().world_position),
+                                orientation: self.cursor_manager.place_orientation(),
+                            });
                         }
                     }
                 }
@@ -225,31 +481,77 @@ impl State {
                                 if self.circuit.component_at(start_position)
                                     == Some(ComponentType::Pin)
                                 {
-                                    self.circuit.delete_component(start_position);
+                                    self.edit(|state| {
+                                        state.circuit.delete_component(start_position)
+                                    });
                                 } else {
-                                    self.circuit.place_component(
-                                        ComponentType::Pin,
-                                        start_position,
-                                        Direction::East,
-                                    );
+                                    self.execute(CircuitCommand::PlaceComponent {
+                                        ty: ComponentType::Pin,
+                                        position: start_position,
+                                        orientation: Direction::East,
+                                    });
                                 }
                             } else {
-                                self.circuit.place_wire(start_position, end_position);
+                                self.execute(CircuitCommand::PlaceWire {
+                                    start: start_position,
+                                    end: end_position,
+                                });
                             }
                             self.cursor_manager.end();
                         }
+                        CursorState::Measure { .. } => {
+                            self.cursor_manager.end();
+                        }
+                        &CursorState::Select { anchor, current } => {
+                            let bounds = cursor::tile_bounds(anchor, current);
+                            self.set_selection(bounds.tiles().collect());
+                            self.cursor_manager.end();
+                        }
+                        &CursorState::MoveSelection {
+                            bounds,
+                            start,
+                            current,
+                        } => {
+                            self.move_selection(bounds, current - start);
+                            self.cursor_manager.end();
+                        }
+                        CursorState::Orbit { .. } => {
+                            self.cursor_manager.end();
+                            self.gfx.window.set_cursor_icon(self.hover_icon());
+                        }
                         _ => {}
                     }
                 }
                 (MouseButton::Right, ElementState::Pressed) => {
                     match &self.cursor_manager.current_state() {
-                        &CursorState::Normal => {
+                        &CursorState::Normal if self.modifiers.ctrl() => {
                             let position = self.viewport.cursor().tile();
+                            self.pending_flood_delete = None;
+                            // The whole drag is one edit, recorded once it ends.
+                            self.circuit.start_journal();
                             self.circuit.delete_all_at(position);
+                            self.cursor_manager.start_erase(&self.viewport);
+                        }
+                        &CursorState::Normal if self.modifiers.alt() => {
+                            let position = self.viewport.cursor().tile();
+                            self.flood_fill_delete(position);
+                        }
+                        &CursorState::Normal => {
+                            let position = self.viewport.cursor().tile();
+                            self.pending_flood_delete = None;
+                            self.edit(|state| state.circuit.delete_at(position));
                         }
                         _ => {}
                     }
                 }
+                (MouseButton::Right, ElementState::Released) => {
+                    if let CursorState::Erase { .. } = self.cursor_manager.current_state() {
+                        self.cursor_manager.end();
+                        if let Some(edit) = self.circuit.take_journal() {
+                            self.history.record(Box::new(edit));
+                        }
+                    }
+                }
                 _ => {}
             },
             WindowEvent::MouseWheel { delta, .. } => match &self.cursor_manager.current_state() {
@@ -258,8 +560,8 @@ impl State {
                         MouseScrollDelta::LineDelta(_x, y) => y,
                         MouseScrollDelta::PixelDelta(position) => position.y as f32 / 16.0,
                     };
-                    let camera = self.viewport.camera_mut();
-                    camera.set_zoom(camera.zoom * camera.zoom_step.powf(delta));
+                    let cursor = self.viewport.cursor().screen_position;
+                    self.viewport.zoom_at(cursor, delta);
                 }
                 _ => {}
             },
@@ -271,6 +573,10 @@ impl State {
                     };
 
                     match keycode {
+                        VirtualKeyCode::W if pressed && self.modifiers.alt() => {
+                            let enable = !self.wireframe;
+                            self.wireframe = self.circuit.set_wireframe_mode(enable) && enable;
+                        }
                         VirtualKeyCode::Up | VirtualKeyCode::W => {
                             self.viewport.camera_mut().pan_up = pressed;
                         }
@@ -301,18 +607,150 @@ impl State {
                         VirtualKeyCode::Key4 if pressed => {
                             self.cursor_manager.set_place_type(ComponentType::Switch);
                         }
+                        VirtualKeyCode::Q if pressed && self.modifiers.shift() => {
+                            let index = GateKind::ALL
+                                .iter()
+                                .position(|&kind| kind == self.gate_kind)
+                                .unwrap();
+                            self.gate_kind = GateKind::ALL[(index + 1) % GateKind::ALL.len()];
+                        }
+                        VirtualKeyCode::Q if pressed => {
+                            let (kind, origin) = (self.gate_kind, self.viewport.cursor().tile());
+                            self.edit(|state| state.circuit.place_gate(kind, origin));
+                        }
                         VirtualKeyCode::E if pressed => {
                             //TODO more intuitive controls?
                             self.circuit.interact(self.viewport.cursor().tile())
                         }
+                        VirtualKeyCode::K if pressed => {
+                            let position = self.viewport.cursor().tile();
+                            if let Some(kind) = self.circuit.pin_kind(position) {
+                                self.edit(|state| {
+                                    state.circuit.set_pin_kind(position, kind.next())
+                                });
+                            }
+                        }
+                        VirtualKeyCode::F if pressed => {
+                            let position = self.viewport.cursor().tile();
+                            let follow = self.viewport.camera().follow_target() != Some(position)
+                                && self
+                                    .viewport
+                                    .cursor()
+                                    .is_over_component(&self.circuit, &self.viewport);
+                            self.viewport.camera_mut().follow(position, follow);
+                        }
+                        VirtualKeyCode::M if pressed && self.modifiers.ctrl() => {
+                            self.edit(|state| state.circuit.merge_collinear_wires());
+                        }
+                        VirtualKeyCode::M => {
+                            self.measure_key = pressed && self.modifiers.shift();
+                        }
                         VirtualKeyCode::R if pressed => {
                             self.cursor_manager.set_place_orientation(
                                 self.cursor_manager.place_orientation().right(),
                             );
                         }
+                        VirtualKeyCode::G if pressed && self.modifiers.ctrl() => {
+                            self.grid.snap = !self.grid.snap;
+                            self.circuit.set_grid_aligner(self.grid);
+                        }
+                        VirtualKeyCode::P
+                            if pressed && self.modifiers.ctrl() && self.modifiers.shift() =>
+                        {
+                            self.edit(|state| state.circuit.remove_isolated_pins());
+                        }
+                        VirtualKeyCode::T if pressed && self.modifiers.ctrl() => {
+                            self.theme_index = (self.theme_index + 1) % self.themes.len();
+                            self.theme_transition =
+                                Some((self.displayed_theme.clone(), Instant::now()));
+                        }
+                        VirtualKeyCode::F5 if pressed => {
+                            if let Err(err) =
+                                CircuitSerializer::save(&mut self.circuit, CIRCUIT_PATH)
+                            {
+                                eprintln!("{:?}", err);
+                            }
+                        }
+                        VirtualKeyCode::F6 if pressed => {
+                            // A load is not undoable, and neither is anything before it.
+                            self.circuit.take_journal();
+                            match CircuitSerializer::load(&mut self.circuit, CIRCUIT_PATH) {
+                                Ok(()) => self.history.clear(),
+                                Err(err) => eprintln!("{:?}", err),
+                            }
+                            // Loading stalls the frame, which would show up as a low rate.
+                            self.frame_counter.reset();
+                        }
+                        VirtualKeyCode::Delete if pressed && !self.selection.is_empty() => {
+                            let selection = std::mem::take(&mut self.selection);
+                            self.edit(|state| {
+                                for position in selection.iter() {
+                                    state.circuit.delete_all_at(position);
+                                }
+                            });
+                            self.cursor_manager.show_selection(None);
+                        }
+                        VirtualKeyCode::C if pressed && self.modifiers.ctrl() => {
+                            let position = self.viewport.cursor().tile();
+                            if let Some(bounds) = self.selection.bounds() {
+                                self.clipboard.copy(self.circuit.serialize_partial(bounds));
+                            } else if let Some(board) = self.circuit.board_at(position) {
+                                self.clipboard.copy(self.circuit.copy_region(&board));
+                            }
+                        }
+                        VirtualKeyCode::V if pressed && self.modifiers.ctrl() => {
+                            if let Some(data) = self.clipboard.contents().cloned() {
+                                let origin = self.viewport.cursor().tile();
+                                self.edit(|state| state.circuit.import_merge(&data, origin));
+                            }
+                        }
+                        VirtualKeyCode::Z if pressed && self.modifiers.ctrl() => {
+                            if self.modifiers.shift() {
+                                self.redo();
+                            } else {
+                                self.undo();
+                            }
+                        }
+                        VirtualKeyCode::Y if pressed && self.modifiers.ctrl() => {
+                            self.redo();
+                        }
+                        VirtualKeyCode::F7 if pressed => {
+                            self.macro_recorder.start(self.viewport.cursor().tile());
+                        }
+                        VirtualKeyCode::F8 if pressed && self.macro_recorder.is_recording() => {
+                            self.macro_recorder.stop();
+                            if let Err(err) = self.macro_recorder.save(MACRO_PATH) {
+                                eprintln!("{:?}", err);
+                            }
+                        }
+                        VirtualKeyCode::F9 if pressed && !self.macro_recorder.is_recording() => {
+                            let origin = self.viewport.cursor().tile();
+                            self.edit(|state| {
+                                state.macro_recorder.play(&mut state.circuit, origin)
+                            });
+                        }
+                        VirtualKeyCode::L if pressed => {
+                            let enabled = !self.circuit.clock_net_display();
+                            self.circuit.set_clock_net_display(enabled);
+                        }
                         VirtualKeyCode::F1 if pressed => {
                             self.draw_help = !self.draw_help;
                         }
+                        VirtualKeyCode::F2 if pressed => {
+                            self.show_debug_markers = !self.show_debug_markers;
+                            // Fade the circuit so that the markers stand out.
+                            self.circuit.set_global_alpha(if self.show_debug_markers {
+                                DEBUG_MARKERS_CIRCUIT_ALPHA
+                            } else {
+                                1.0
+                            });
+                        }
+                        VirtualKeyCode::F4 if pressed => {
+                            profiler::print_report();
+                        }
+                        VirtualKeyCode::F3 if pressed => {
+                            self.frame_timing_graph.visible = !self.frame_timing_graph.visible;
+                        }
                         _ => {}
                     }
                 }
@@ -321,18 +759,150 @@ impl State {
         }
     }
 
+    fn execute(&mut self, command: CircuitCommand) {
+        if self.edit(|state| command.apply(&mut state.circuit)) {
+            self.macro_recorder.record(command);
+        }
+    }
+
+    fn set_selection(&mut self, selection: Selection) {
+        self.cursor_manager.show_selection(selection.bounds());
+        self.selection = selection;
+    }
+
+    /// Moves everything in the selected `bounds` by `offset`, and the selection with it.
+    ///
+    /// Wires leaving the selection are deleted, like with [`Circuit::delete_all_at`].
+    fn move_selection(&mut self, bounds: RectI, offset: IVec2) {
+        if offset == IVec2::ZERO {
+            return;
+        }
+        let data = self.circuit.serialize_partial(bounds);
+        self.edit(|state| {
+            for position in bounds.tiles() {
+                state.circuit.delete_all_at(position);
+            }
+            state.circuit.import_merge(&data, bounds.min + offset);
+        });
+        self.set_selection(
+            self.selection
+                .iter()
+                .map(|position| position + offset)
+                .collect(),
+        );
+    }
+
+    /// Runs `edit` and makes it undoable if it changed the circuit.
+    ///
+    /// During an erase drag, the changes become part of the drag's edit instead.
+    fn edit<R>(&mut self, edit: impl FnOnce(&mut Self) -> R) -> R {
+        let recording = self.circuit.start_journal();
+        let result = edit(self);
+        if recording {
+            if let Some(edit) = self.circuit.take_journal() {
+                self.history.record(Box::new(edit));
+            }
+        }
+        result
+    }
+
+    fn undo(&mut self) {
+        self.history.undo(&mut self.circuit);
+    }
+
+    fn redo(&mut self) {
+        self.history.redo(&mut self.circuit);
+    }
+
     fn update(&mut self) {
+        let _span = profiler::span("State::update");
         let now = Instant::now();
         let dt = now - self.last_update;
         self.last_update = now;
 
+        self.viewport.begin_frame();
         self.cursor_manager
-            .update(&mut self.viewport, &self.circuit);
+            .update(&mut self.viewport, &self.circuit, &self.grid);
+        for position in self.cursor_manager.erase_path(&self.viewport) {
+            self.circuit.delete_all_at(position);
+        }
         self.viewport.update(dt);
+
+        if let Some((from, start)) = &self.theme_transition {
+            let t = start.elapsed().as_secs_f32() / THEME_TRANSITION_DURATION.as_secs_f32();
+            self.displayed_theme = Theme::lerp(from, &self.themes[self.theme_index], t);
+            self.circuit.apply_theme(&self.displayed_theme);
+            if t >= 1.0 {
+                self.theme_transition = None;
+            }
+        }
+
+        let hovering = self
+            .viewport
+            .cursor()
+            .is_over_component(&self.circuit, &self.viewport);
+        if hovering != self.hovering_component {
+            self.hovering_component = hovering;
+            if !matches!(
+                self.cursor_manager.current_state(),
+                CursorState::Pan { .. } | CursorState::Orbit { .. }
+            ) {
+                self.gfx.window.set_cursor_icon(self.hover_icon());
+            }
+        }
+
+        let cursor_position = self.viewport.cursor().screen_position;
+        let cursor_moved = cursor_position != self.last_cursor_position;
+        self.last_cursor_position = cursor_position;
+        let hovered = self.circuit.gate_at(self.viewport.cursor().tile());
+        self.tooltip
+            .update(cursor_moved, hovered.is_some(), dt.as_secs_f32());
+        if let Some(gate) = hovered {
+            self.tooltip.content = format!("{:?} facing {:?}", gate.ty, gate.orientation);
+        }
+
+        let unsaved = self.circuit.has_unsaved_changes();
+        if unsaved != self.title_unsaved {
+            self.title_unsaved = unsaved;
+            self.gfx
+                .window
+                .set_title(if unsaved { "FlipFlop *" } else { "FlipFlop" });
+        }
+        if !unsaved {
+            self.pending_close = false;
+        }
+
+        self.debug_markers = if self.show_debug_markers {
+            self.circuit.validate()
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Deletes the net under `position`, asking for confirmation first if it is large.
+    fn flood_fill_delete(&mut self, position: IVec2) {
+        let count = self.circuit.net_element_count(position);
+        let confirmed = self.pending_flood_delete == Some((position, count));
+        if count > self.flood_delete_confirm_threshold && !confirmed {
+            self.pending_flood_delete = Some((position, count));
+            return;
+        }
+        self.pending_flood_delete = None;
+        self.edit(|state| state.circuit.flood_fill_delete(position));
+    }
+
+    fn hover_icon(&self) -> CursorIcon {
+        if self.hovering_component {
+            CursorIcon::Hand
+        } else {
+            CursorIcon::Default
+        }
     }
 
     fn redraw(&mut self) -> anyhow::Result<()> {
+        let _span = profiler::span("State::redraw");
         self.frame_counter.tick();
+        self.frame_timing_graph.tick();
 
         let frame = loop {
             match self.gfx.surface.get_current_texture() {
@@ -366,6 +936,27 @@ impl State {
                 &frame_view,
                 &self.depth_texture_view,
             );
+            self.frame_timing_graph
+                .draw(&self.viewport, &mut encoder, &frame_view);
+            self.power_bar.draw(
+                self.circuit.net_power_percentage(),
+                &self.viewport,
+                &mut encoder,
+                &frame_view,
+            );
+        }
+
+        self.queue_debug_markers();
+        self.queue_tooltip();
+        if let Some(label) = self.cursor_manager.current_state().measure_label() {
+            const LABEL_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+            self.glyph_brush.queue(Section {
+                screen_position: (self.viewport.cursor().screen_position + LABEL_OFFSET).into(),
+                text: vec![Text::new(&label)
+                    .with_color([1.0, 0.0, 1.0, 1.0])
+                    .with_scale(18.0)],
+                ..Default::default()
+            });
         }
 
         let size = self.gfx.window.inner_size();
@@ -407,30 +998,134 @@ impl State {
         Ok(())
     }
 
+    /// Queues a blinking "!" over every tile flagged by the circuit validation, in yellow for
+    /// floating inputs and red otherwise.
+    fn queue_debug_markers(&mut self) {
+        const BLINK_FREQUENCY: f32 = 2.0;
+
+        let time = self.start_time.elapsed().as_secs_f32();
+        let alpha = 0.5 + 0.5 * (time * BLINK_FREQUENCY * std::f32::consts::TAU).sin();
+        let scale = self.viewport.camera().zoom;
+        let layout = Layout::default_single_line()
+            .h_align(HorizontalAlign::Center)
+            .v_align(VerticalAlign::Center);
+        for (position, kind) in &self.debug_markers {
+            // Floating inputs are usually unfinished wiring rather than a real fault.
+            let color = match kind {
+                DebugMarkerKind::FloatingInput => [1.0, 1.0, 0.0, alpha],
+                _ => [1.0, 0.0, 0.0, alpha],
+            };
+            let center = self
+                .viewport
+                .world_to_screen(position.as_vec2() + Vec2::splat(0.5));
+            self.glyph_brush.queue(Section {
+                screen_position: center.into(),
+                text: vec![Text::new("!").with_color(color).with_scale(scale)],
+                layout,
+                ..Default::default()
+            });
+        }
+    }
+
+    fn queue_tooltip(&mut self) {
+        if !self.tooltip.visible {
+            return;
+        }
+        let mut section = Section {
+            text: vec![Text::new(&self.tooltip.content)
+                .with_color([1.0, 1.0, 1.0, self.tooltip.opacity])
+                .with_scale(18.0)],
+            ..Default::default()
+        };
+        let size = match self.glyph_brush.glyph_bounds(&section) {
+            Some(bounds) => Vec2::new(bounds.width(), bounds.height()),
+            None => return,
+        };
+        self.tooltip.screen_position = Tooltip::clamped_position(
+            self.viewport.cursor().screen_position,
+            size,
+            self.viewport.size(),
+        );
+        section.screen_position = self.tooltip.screen_position.into();
+        self.glyph_brush.queue(section);
+    }
+
     fn debug_text(&self) -> String {
         let fps = self.frame_counter.rate();
         let cursor_pos = <(f32, f32)>::from(self.viewport.cursor().screen_position);
         let world_pos = <(f32, f32)>::from(self.viewport.cursor().world_position);
         let cursor_tile = <(i32, i32)>::from(self.viewport.cursor().tile());
         let tile_debug_info = self.circuit.tile_debug_info(self.viewport.cursor().tile());
+        let problems: String = self
+            .debug_markers
+            .iter()
+            .filter(|(position, _kind)| *position == self.viewport.cursor().tile())
+            .map(|(_position, kind)| format!("Problem: {:?}\n", kind))
+            .collect();
+        let confirmation = match self.pending_flood_delete {
+            Some((_position, count)) => {
+                format!("Alt+right click again to delete {} elements\n", count)
+            }
+            None => String::new(),
+        };
+        let close_confirmation = if self.pending_close {
+            "Unsaved changes: F5 to save, close again to discard\n"
+        } else {
+            ""
+        };
+        let clipboard = if self.clipboard.is_empty() {
+            ""
+        } else {
+            "CLIP\n"
+        };
 
         format!(
             "FPS: {:.0}\n\
+            Powered: {:.0}%\n\
+            Longest Wire: {}\n\
+            Board Area: {}\n\
+            Gate: {:?}\n\
+            {}\
             Cursor: {:.0?}\n\
             World: {:.2?}\n\
             Tile: {:?}\n\
-            {}",
-            fps, cursor_pos, world_pos, cursor_tile, tile_debug_info,
+            {}{}{}{}",
+            fps,
+            self.circuit.net_power_percentage(),
+            self.circuit.longest_wire().unwrap_or(0),
+            self.circuit.total_board_area(),
+            self.gate_kind,
+            clipboard,
+            cursor_pos,
+            world_pos,
+            cursor_tile,
+            tile_debug_info,
+            problems,
+            confirmation,
+            close_confirmation,
         )
     }
 
     fn reconfigure(&mut self) {
         self.gfx.reconfigure();
+        self.viewport.on_resize(self.gfx.window.inner_size());
         self.depth_texture = create_depth_texture(&self.gfx);
         self.depth_texture_view = self.depth_texture.create_view(&Default::default());
     }
 }
 
+/// Whether to render with a software adapter, either because `--software-render` was passed or
+/// because we are running in CI.
+#[cfg(feature = "software-render")]
+fn software_render_requested() -> bool {
+    std::env::args().any(|arg| arg == "--software-render") || std::env::var_os("CI").is_some()
+}
+
+#[cfg(not(feature = "software-render"))]
+fn software_render_requested() -> bool {
+    false
+}
+
 fn main() -> anyhow::Result<()> {
     env_logger::init();
 
@@ -448,17 +1143,25 @@ fn main() -> anyhow::Result<()> {
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::RedrawRequested(..) => {
+                state.fps_limiter.frame_started();
                 state.update();
                 state.redraw().unwrap();
             }
             Event::WindowEvent { event, .. } => {
                 state.handle_window_event(event);
             }
-            Event::MainEventsCleared => {
+            Event::DeviceEvent { device_id, event } => {
+                state.handle_device_event(device_id, event);
+            }
+            Event::MainEventsCleared if state.fps_limiter.should_redraw() => {
                 state.gfx.window.request_redraw();
             }
+            Event::LoopDestroyed => {
+                profiler::print_report();
+            }
             _ => {}
         }
+        *control_flow = state.fps_limiter.control_flow();
         if state.should_close {
             *control_flow = ControlFlow::Exit;
         }
@@ -0,0 +1,60 @@
+use crate::screen_bars::{self, Bar, ScreenBars};
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use glam::{Vec2, Vec4};
+
+const SIZE: Vec2 = Vec2::new(120.0, 8.0);
+const MARGIN: f32 = 8.0;
+const BACKGROUND_COLOR: Vec4 = Vec4::new(0.2, 0.2, 0.2, 0.8);
+const FILL_COLOR: Vec4 = Vec4::new(0.0, 0.8, 0.0, 0.8);
+
+/// Shows [`crate::circuit::Circuit::net_power_percentage`] as a progress bar in the bottom-right
+/// corner of the window.
+pub struct PowerBar {
+    bars: ScreenBars,
+}
+
+impl PowerBar {
+    pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> Self {
+        Self {
+            bars: ScreenBars::new(gfx, viewport, 2),
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        percentage: f32,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+    ) {
+        let bars = Self::bars(percentage, viewport.size());
+        self.bars.draw(&bars, viewport, encoder, frame_view);
+    }
+
+    /// The background, then the filled part on top of it.
+    fn bars(percentage: f32, window_size: Vec2) -> [Bar; 2] {
+        let position = window_size - SIZE - MARGIN;
+        let fill = SIZE * Vec2::new(percentage.clamp(0.0, 100.0) / 100.0, 1.0);
+        [
+            screen_bars::bar(position, SIZE, BACKGROUND_COLOR),
+            screen_bars::bar(position, fill, FILL_COLOR),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PowerBar, SIZE};
+    use glam::Vec2;
+
+    #[test]
+    fn fill_follows_percentage() {
+        let window = Vec2::new(800.0, 600.0);
+        let [background, fill] = PowerBar::bars(25.0, window);
+        assert_eq!(background[2], SIZE.x);
+        assert_eq!(fill[2], SIZE.x / 4.0);
+        assert_eq!(fill[0], background[0]);
+        assert_eq!(PowerBar::bars(150.0, window)[1][2], SIZE.x);
+    }
+}
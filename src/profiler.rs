@@ -0,0 +1,148 @@
+//! A minimal span profiler for finding bottlenecks.
+//!
+//! Spans are only recorded in debug builds; in release builds [`span`] returns an empty guard and
+//! the report is always empty.
+
+use std::time::Duration;
+
+/// The number of spans printed by [`print_report`].
+pub const REPORT_SIZE: usize = 10;
+
+/// Timing totals for all spans of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanStats {
+    pub name: &'static str,
+    pub count: u32,
+    pub total: Duration,
+}
+
+/// Starts timing a span, which ends when the returned guard is dropped.
+pub fn span(name: &'static str) -> ProfilerSpan {
+    ProfilerSpan::new(name)
+}
+
+/// The recorded spans on this thread, most expensive first.
+pub fn report() -> Vec<SpanStats> {
+    let mut stats = imp::stats();
+    stats.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+    stats
+}
+
+/// Prints the [`REPORT_SIZE`] most expensive spans to stdout.
+pub fn print_report() {
+    let stats = report();
+    if stats.is_empty() {
+        return;
+    }
+    println!(
+        "{:<24} {:>8} {:>12} {:>12}",
+        "Span", "Count", "Total", "Average"
+    );
+    for span in stats.iter().take(REPORT_SIZE) {
+        println!(
+            "{:<24} {:>8} {:>12.3?} {:>12.3?}",
+            span.name,
+            span.count,
+            span.total,
+            span.total / span.count.max(1),
+        );
+    }
+}
+
+#[cfg(debug_assertions)]
+pub struct ProfilerSpan {
+    name: &'static str,
+    start: std::time::Instant,
+}
+
+#[cfg(debug_assertions)]
+impl ProfilerSpan {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for ProfilerSpan {
+    fn drop(&mut self) {
+        imp::record(self.name, self.start.elapsed());
+    }
+}
+
+#[cfg(not(debug_assertions))]
+pub struct ProfilerSpan;
+
+#[cfg(not(debug_assertions))]
+impl ProfilerSpan {
+    fn new(_name: &'static str) -> Self {
+        Self
+    }
+}
+
+#[cfg(debug_assertions)]
+mod imp {
+    use super::SpanStats;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    thread_local! {
+        static SPANS: RefCell<HashMap<&'static str, (u32, Duration)>> =
+            RefCell::new(HashMap::new());
+    }
+
+    pub fn record(name: &'static str, elapsed: Duration) {
+        SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+            let (count, total) = spans.entry(name).or_default();
+            *count += 1;
+            *total += elapsed;
+        });
+    }
+
+    pub fn stats() -> Vec<SpanStats> {
+        SPANS.with(|spans| {
+            spans
+                .borrow()
+                .iter()
+                .map(|(&name, &(count, total))| SpanStats { name, count, total })
+                .collect()
+        })
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod imp {
+    use super::SpanStats;
+
+    pub fn stats() -> Vec<SpanStats> {
+        Vec::new()
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::{report, span};
+    use std::time::Duration;
+
+    #[test]
+    fn records_spans() {
+        for _ in 0..3 {
+            let _span = span("cheap");
+        }
+        {
+            let _span = span("expensive");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let stats = report();
+        assert_eq!(stats[0].name, "expensive");
+        assert_eq!(stats[0].count, 1);
+        assert!(stats[0].total >= Duration::from_millis(5));
+        let cheap = stats.iter().find(|span| span.name == "cheap").unwrap();
+        assert_eq!(cheap.count, 3);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::circuit::{Circuit, ComponentType};
+use crate::direction::Direction;
+use anyhow::Context;
+use glam::IVec2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// An editing operation that can be recorded and replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitCommand {
+    PlaceWire {
+        start: IVec2,
+        end: IVec2,
+    },
+    PlaceComponent {
+        ty: ComponentType,
+        position: IVec2,
+        orientation: Direction,
+    },
+}
+
+impl CircuitCommand {
+    pub fn offset(self, offset: IVec2) -> Self {
+        match self {
+            Self::PlaceWire { start, end } => Self::PlaceWire {
+                start: start + offset,
+                end: end + offset,
+            },
+            Self::PlaceComponent {
+                ty,
+                position,
+                orientation,
+            } => Self::PlaceComponent {
+                ty,
+                position: position + offset,
+                orientation,
+            },
+        }
+    }
+
+    pub fn apply(&self, circuit: &mut Circuit) -> bool {
+        match *self {
+            Self::PlaceWire { start, end } => circuit.place_wire(start, end),
+            Self::PlaceComponent {
+                ty,
+                position,
+                orientation,
+            } => circuit.place_component(ty, position, orientation),
+        }
+    }
+}
+
+/// Records placement commands so they can be stamped elsewhere.
+///
+/// Commands are stored relative to the tile the recording was started at, and played back
+/// relative to the given tile.
+#[derive(Default)]
+pub struct MacroRecorder {
+    origin: Option<IVec2>,
+    commands: Vec<CircuitCommand>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read macro {}", path.display()))?;
+        let commands = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid macro {}", path.display()))?;
+        Ok(Self {
+            origin: None,
+            commands,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(&self.commands)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write macro {}", path.display()))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.origin.is_some()
+    }
+
+    pub fn commands(&self) -> &[CircuitCommand] {
+        &self.commands
+    }
+
+    /// Discards the current macro and starts recording a new one.
+    pub fn start(&mut self, origin: IVec2) {
+        self.origin = Some(origin);
+        self.commands.clear();
+    }
+
+    pub fn stop(&mut self) {
+        self.origin = None;
+    }
+
+    /// Adds the command to the macro, if recording.
+    pub fn record(&mut self, command: CircuitCommand) {
+        if let Some(origin) = self.origin {
+            self.commands.push(command.offset(-origin));
+        }
+    }
+
+    pub fn play(&self, circuit: &mut Circuit, origin: IVec2) {
+        for command in &self.commands {
+            command.offset(origin).apply(circuit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitCommand, MacroRecorder};
+    use crate::circuit::ComponentType;
+    use crate::direction::Direction;
+    use glam::IVec2;
+
+    #[test]
+    fn records_relative_to_origin() {
+        let mut recorder = MacroRecorder::new();
+        recorder.record(CircuitCommand::PlaceWire {
+            start: IVec2::ZERO,
+            end: IVec2::X,
+        });
+        assert!(recorder.commands().is_empty());
+
+        recorder.start(IVec2::new(5, 5));
+        recorder.record(CircuitCommand::PlaceComponent {
+            ty: ComponentType::Flip,
+            position: IVec2::new(6, 4),
+            orientation: Direction::North,
+        });
+        recorder.stop();
+        recorder.record(CircuitCommand::PlaceWire {
+            start: IVec2::ZERO,
+            end: IVec2::X,
+        });
+
+        assert_eq!(
+            recorder.commands(),
+            &[CircuitCommand::PlaceComponent {
+                ty: ComponentType::Flip,
+                position: IVec2::new(1, -1),
+                orientation: Direction::North,
+            }]
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let commands = vec![
+            CircuitCommand::PlaceWire {
+                start: IVec2::new(0, 0),
+                end: IVec2::new(0, 3),
+            },
+            CircuitCommand::PlaceComponent {
+                ty: ComponentType::Switch,
+                position: IVec2::new(-1, 2),
+                orientation: Direction::West,
+            },
+        ];
+        let json = serde_json::to_string(&commands).unwrap();
+        let parsed: Vec<CircuitCommand> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, commands);
+    }
+}
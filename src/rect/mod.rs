@@ -1,5 +1,6 @@
+use crate::color_utils;
 use crate::direction::Direction;
-use crate::instance::InstanceManager;
+use crate::instance::{self, contiguous_ranges, InstanceManager};
 use crate::simulation::Simulation;
 use crate::viewport::Viewport;
 use crate::GraphicsContext;
@@ -7,8 +8,11 @@ use bitvec::prelude::{BitVec, Lsb0};
 use bytemuck::{Pod, Zeroable};
 use glam::{IVec2, Vec2, Vec4};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use wgpu::util::DeviceExt;
 
+#[derive(PartialEq, Eq, Hash)]
 pub struct Handle {
     inner: crate::instance::Handle<Instance>,
 }
@@ -17,6 +21,14 @@ impl Handle {
     pub fn set(&self, rect: &Rect) {
         self.inner.set(Instance::new(rect));
     }
+
+    /// A handle not attached to any renderer, whose updates go nowhere.
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self {
+            inner: crate::instance::Handle::detached(),
+        }
+    }
 }
 
 #[repr(C)]
@@ -49,15 +61,17 @@ struct Instance {
     size: [f32; 2],
     color: [f32; 4],
     cluster_index: u32,
+    shape: u32,
 }
 
-static INSTANCE_ATTRIBUTES: Lazy<[wgpu::VertexAttribute; 5]> = Lazy::new(|| {
+static INSTANCE_ATTRIBUTES: Lazy<[wgpu::VertexAttribute; 6]> = Lazy::new(|| {
     wgpu::vertex_attr_array![
         1 => Float32x2,
         2 => Float32,
         3 => Float32x2,
         4 => Float32x4,
         5 => Uint32,
+        6 => Uint32,
     ]
 });
 
@@ -77,8 +91,68 @@ impl Instance {
             size: rect.size.into(),
             color: rect.color.color().into(),
             cluster_index: rect.color.cluster_index(),
+            shape: rect.shape.index(),
+        }
+    }
+
+    fn is_wire(&self) -> bool {
+        self.cluster_index != 0xffffffff
+    }
+}
+
+/// Whether rects with the given [`Instance::cluster_index`] are drawn in their powered color.
+fn is_powered(cluster_states: &BitVec<u32, Lsb0>, cluster_index: u32) -> bool {
+    // Same lookup as the shader: the low bit inverts, the rest index the state bits.
+    let state = cluster_states
+        .get((cluster_index >> 1) as usize)
+        .is_some_and(|bit| *bit);
+    state ^ (cluster_index & 1 != 0)
+}
+
+/// Tallies of the wire-colored rects, kept up to date from [`instance::Change`]s.
+#[derive(Default)]
+struct WireCounts {
+    /// The number of wire-colored rects by [`Instance::cluster_index`].
+    clusters: HashMap<u32, usize>,
+    total: usize,
+    powered: usize,
+}
+
+impl WireCounts {
+    /// Counts a rect added or removed, drawn with `cluster_states`. Changes must be applied in
+    /// the order they happened, since a rect can be added and changed again before a count.
+    fn apply(&mut self, change: instance::Change<Instance>, cluster_states: &BitVec<u32, Lsb0>) {
+        match change {
+            instance::Change::Added(instance) if instance.is_wire() => {
+                *self.clusters.entry(instance.cluster_index).or_default() += 1;
+                self.total += 1;
+                if is_powered(cluster_states, instance.cluster_index) {
+                    self.powered += 1;
+                }
+            }
+            instance::Change::Removed(instance) if instance.is_wire() => {
+                let count = self.clusters.get_mut(&instance.cluster_index).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.clusters.remove(&instance.cluster_index);
+                }
+                self.total -= 1;
+                if is_powered(cluster_states, instance.cluster_index) {
+                    self.powered -= 1;
+                }
+            }
+            _ => {}
         }
     }
+
+    fn recount_powered(&mut self, cluster_states: &BitVec<u32, Lsb0>) {
+        self.powered = self
+            .clusters
+            .iter()
+            .filter(|(&cluster_index, _)| is_powered(cluster_states, cluster_index))
+            .map(|(_, &count)| count)
+            .sum();
+    }
 }
 
 const VERTICES: &[Vertex] = &[
@@ -102,20 +176,137 @@ const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
 #[repr(C)]
 struct WirePalette {
     buffer: [[f32; 4]; 2],
+    end_buffer: [[f32; 4]; 2],
+    gradient: u32,
+    padding: [u32; 3],
+}
+
+impl WirePalette {
+    fn new(wire_color: &WireColor) -> Self {
+        Self {
+            buffer: [wire_color.off_color.into(), wire_color.on_color.into()],
+            end_buffer: [
+                wire_color.off_end_color.into(),
+                wire_color.on_end_color.into(),
+            ],
+            gradient: wire_color.gradient as u32,
+            padding: [0; 3],
+        }
+    }
+}
+
+// The layout of the `Dash` shader struct, padded to 16 bytes. `all_rects` dashes every rect
+// instead of only wires.
+fn dash_uniform(dash_len: f32, gap_len: f32, all_rects: bool) -> [u32; 4] {
+    [
+        dash_len.max(0.0).to_bits(),
+        gap_len.max(0.0).to_bits(),
+        all_rects as u32,
+        0,
+    ]
+}
+
+// The alpha scale uniform is padded to 16 bytes.
+fn alpha_scale_uniform(alpha: f32) -> [f32; 4] {
+    [alpha.clamp(0.0, 1.0), 0.0, 0.0, 0.0]
+}
+
+/// The colors used to draw wires, depending on their power state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WireColor {
+    pub off_color: Vec4,
+    pub on_color: Vec4,
+    /// If true, wires blend from their base color to the corresponding end color along their
+    /// length (in the direction of increasing X or Y).
+    pub gradient: bool,
+    pub off_end_color: Vec4,
+    pub on_end_color: Vec4,
+}
+
+impl WireColor {
+    /// Blends component-wise from `a` at `t = 0` to `b` at `t = 1`.
+    ///
+    /// In between, the gradient is enabled if either color uses it, so the end colors blend
+    /// smoothly too.
+    pub fn lerp(a: &WireColor, b: &WireColor, t: f32) -> WireColor {
+        let t = t.clamp(0.0, 1.0);
+        let gradient = if t <= 0.0 {
+            a.gradient
+        } else if t >= 1.0 {
+            b.gradient
+        } else {
+            a.gradient || b.gradient
+        };
+        WireColor {
+            off_color: a.off_color.lerp(b.off_color, t),
+            on_color: a.on_color.lerp(b.on_color, t),
+            gradient,
+            off_end_color: a.off_end_color.lerp(b.off_end_color, t),
+            on_end_color: a.on_end_color.lerp(b.on_end_color, t),
+        }
+    }
+
+    /// Rotates the hue of every color by `degrees`, e.g. to give nets distinct colors by
+    /// spacing their shifts evenly around 360 degrees.
+    pub fn with_hue_shift(&self, degrees: f32) -> WireColor {
+        WireColor {
+            off_color: color_utils::shift_hue(self.off_color, degrees),
+            on_color: color_utils::shift_hue(self.on_color, degrees),
+            gradient: self.gradient,
+            off_end_color: color_utils::shift_hue(self.off_end_color, degrees),
+            on_end_color: color_utils::shift_hue(self.on_end_color, degrees),
+        }
+    }
+}
+
+impl Default for WireColor {
+    fn default() -> Self {
+        Self {
+            off_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            on_color: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            gradient: false,
+            off_end_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            on_end_color: Vec4::new(1.0, 0.0, 0.0, 1.0),
+        }
+    }
 }
 
 pub struct RectRenderer {
     gfx: GraphicsContext,
     render_pipeline: wgpu::RenderPipeline,
+    // Kept to rebuild the render pipeline, see `set_wireframe_mode`.
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_module: wgpu::ShaderModule,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     cluster_state_buffer: wgpu::Buffer,
+    wire_palette_buffer: wgpu::Buffer,
+    alpha_scale_buffer: wgpu::Buffer,
+    dash_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
+    highlight_pipeline: wgpu::RenderPipeline,
+    outline_pipeline: wgpu::RenderPipeline,
+    highlight_buffer: wgpu::Buffer,
+    highlight_bind_group: wgpu::BindGroup,
+    /// Persistent highlights by instance handle id, see [`Self::highlight`]. Ordered so that
+    /// colors get the same slots from one run to the next.
+    highlights: BTreeMap<u64, [f32; 4]>,
     instances: InstanceManager<Instance>,
+    cluster_states: BitVec<u32, Lsb0>,
+    wire_counts: WireCounts,
 }
 
 impl RectRenderer {
     pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> Self {
+        Self::new_with_capacity(gfx, viewport, 0)
+    }
+
+    /// Creates a renderer with GPU buffer space for `initial_capacity` rects already allocated.
+    pub fn new_with_capacity(
+        gfx: &GraphicsContext,
+        viewport: &Viewport,
+        initial_capacity: usize,
+    ) -> Self {
         let bind_group_layout =
             gfx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -141,6 +332,26 @@ impl RectRenderer {
                             },
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: true,
+                                min_binding_size: wgpu::BufferSize::new(DASH_UNIFORM_SIZE),
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -154,14 +365,92 @@ impl RectRenderer {
         let shader_module = gfx
             .device
             .create_shader_module(wgpu::include_wgsl!("rect.wgsl"));
-        let render_pipeline = gfx
+        let render_pipeline = create_render_pipeline(
+            gfx,
+            &pipeline_layout,
+            &shader_module,
+            wgpu::PolygonMode::Fill,
+        );
+
+        let highlight_bind_group_layout =
+            gfx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("RectRenderer.highlight_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(HIGHLIGHT_UNIFORM_SIZE),
+                        },
+                        count: None,
+                    }],
+                });
+        let highlight_pipeline_layout =
+            gfx.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("RectRenderer.highlight_pipeline_layout"),
+                    bind_group_layouts: &[
+                        viewport.bind_group_layout(),
+                        &bind_group_layout,
+                        &highlight_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        // Highlights are drawn over the regular pass with additive blending, at the same depth as
+        // the instances they cover.
+        let highlight_pipeline =
+            gfx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("RectRenderer.highlight_pipeline"),
+                    layout: Some(&highlight_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::buffer_layout(), Instance::buffer_layout()],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        front_face: wgpu::FrontFace::Cw,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: gfx.depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::GreaterEqual,
+                        stencil: Default::default(),
+                        bias: Default::default(),
+                    }),
+                    multisample: Default::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_highlight",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gfx.render_format,
+                            blend: Some(wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                                    dst_factor: wgpu::BlendFactor::One,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::OVER,
+                            }),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                });
+        // Outlines are drawn before the regular pass without writing depth, so that the rects
+        // drawn afterwards cover all but the border.
+        let outline_pipeline = gfx
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("RectRenderer.render_pipeline"),
-                layout: Some(&pipeline_layout),
+                label: Some("RectRenderer.outline_pipeline"),
+                layout: Some(&highlight_pipeline_layout),
                 vertex: wgpu::VertexState {
                     module: &shader_module,
-                    entry_point: "vs_main",
+                    entry_point: "vs_outline",
                     buffers: &[Vertex::buffer_layout(), Instance::buffer_layout()],
                 },
                 primitive: wgpu::PrimitiveState {
@@ -171,21 +460,18 @@ impl RectRenderer {
                 },
                 depth_stencil: Some(wgpu::DepthStencilState {
                     format: gfx.depth_format,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::GreaterEqual,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
                     stencil: Default::default(),
                     bias: Default::default(),
                 }),
                 multisample: Default::default(),
                 fragment: Some(wgpu::FragmentState {
                     module: &shader_module,
-                    entry_point: "fs_main",
+                    entry_point: "fs_highlight",
                     targets: &[Some(wgpu::ColorTargetState {
                         format: gfx.render_format,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent::REPLACE,
-                            alpha: wgpu::BlendComponent::REPLACE,
-                        }),
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
                 }),
@@ -215,12 +501,27 @@ impl RectRenderer {
             gfx.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("RectRenderer.wire_palette_buffer"),
-                    contents: bytemuck::bytes_of(&WirePalette {
-                        buffer: [[0.0, 0.0, 0.0, 1.0], [1.0, 0.0, 0.0, 1.0]],
-                    }),
-                    usage: wgpu::BufferUsages::UNIFORM,
+                    contents: bytemuck::bytes_of(&WirePalette::new(&Default::default())),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
 
+        let alpha_scale_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("RectRenderer.alpha_scale_buffer"),
+                contents: bytemuck::bytes_of(&alpha_scale_uniform(1.0)),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        // Slot 0 stays zeroed, for solid rects; slot 1 holds the pattern of `draw_dashed`.
+        let dash_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("RectRenderer.dash_buffer"),
+                contents: &[0; (2 * HIGHLIGHT_SLOT_STRIDE) as usize],
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
         let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("RectRenderer.bind_group"),
             layout: &bind_group_layout,
@@ -233,19 +534,70 @@ impl RectRenderer {
                     binding: 1,
                     resource: wire_palette_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: alpha_scale_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &dash_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(DASH_UNIFORM_SIZE),
+                    }),
+                },
             ],
         });
 
-        let instances = InstanceManager::new(gfx);
+        let highlight_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("RectRenderer.highlight_buffer"),
+                contents: &[0; ((HIGHLIGHT_SLOTS + 1) * HIGHLIGHT_SLOT_STRIDE) as usize],
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let highlight_bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("RectRenderer.highlight_bind_group"),
+            layout: &highlight_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &highlight_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(HIGHLIGHT_UNIFORM_SIZE),
+                }),
+            }],
+        });
+
+        let mut instances = InstanceManager::with_capacity(gfx, initial_capacity);
+        // Keeps the wire counts up to date, see `update_wire_counts`.
+        instances.track_changes();
+        gfx.queue.write_buffer(
+            &highlight_buffer,
+            0,
+            bytemuck::bytes_of(&<[f32; 4]>::from(DEFAULT_HIGHLIGHT_COLOR)),
+        );
 
         Self {
             gfx: gfx.clone(),
             render_pipeline,
+            pipeline_layout,
+            shader_module,
             vertex_buffer,
             index_buffer,
             cluster_state_buffer,
+            wire_palette_buffer,
+            alpha_scale_buffer,
+            dash_buffer,
             bind_group,
+            highlight_pipeline,
+            outline_pipeline,
+            highlight_buffer,
+            highlight_bind_group,
+            highlights: BTreeMap::new(),
             instances,
+            cluster_states: BitVec::new(),
+            wire_counts: WireCounts::default(),
         }
     }
 
@@ -254,7 +606,97 @@ impl RectRenderer {
         Handle { inner }
     }
 
+    /// Sets several rects at once. Like [`Handle::set`], the changes are uploaded at the next
+    /// draw, with adjacent rects written together.
+    pub fn update_batch(&mut self, updates: &[(&Handle, Rect)]) {
+        for (handle, rect) in updates {
+            handle.set(rect);
+        }
+    }
+
+    pub fn defragment(&mut self) {
+        self.instances.defragment();
+    }
+
+    /// Allocates space for at least `additional` more rects, see [`InstanceManager::reserve`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.instances.reserve(additional);
+    }
+
+    pub fn set_wire_color(&mut self, wire_color: &WireColor) {
+        self.gfx.queue.write_buffer(
+            &self.wire_palette_buffer,
+            0,
+            bytemuck::bytes_of(&WirePalette::new(wire_color)),
+        );
+    }
+
+    /// Scales the opacity of everything drawn by this renderer, e.g. to fade it behind an overlay.
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        self.gfx.queue.write_buffer(
+            &self.alpha_scale_buffer,
+            0,
+            bytemuck::bytes_of(&alpha_scale_uniform(alpha)),
+        );
+    }
+
+    /// Draws only the edges of the triangles making up each rect when enabled, to debug geometry.
+    ///
+    /// Returns false, leaving the fill mode in place, if the device does not support drawing
+    /// lines.
+    pub fn set_wireframe_mode(&mut self, enabled: bool) -> bool {
+        let polygon_mode = if enabled {
+            if !self
+                .gfx
+                .device
+                .features()
+                .contains(wgpu::Features::POLYGON_MODE_LINE)
+            {
+                return false;
+            }
+            wgpu::PolygonMode::Line
+        } else {
+            wgpu::PolygonMode::Fill
+        };
+        self.render_pipeline = create_render_pipeline(
+            &self.gfx,
+            &self.pipeline_layout,
+            &self.shader_module,
+            polygon_mode,
+        );
+        true
+    }
+
+    /// Sets the color added on top of highlighted rects, see [`Self::draw_with_highlight`].
+    pub fn set_highlight_color(&mut self, color: Vec4) {
+        self.gfx.queue.write_buffer(
+            &self.highlight_buffer,
+            0,
+            bytemuck::bytes_of(&<[f32; 4]>::from(color)),
+        );
+    }
+
+    /// Keeps `handle` highlighted with `color` in every draw until [`Self::unhighlight`] is called
+    /// or the handle is dropped, e.g. to mark a selection.
+    ///
+    /// At most [`HIGHLIGHT_SLOTS`] - 1 distinct colors are shown at once, taken in the order of
+    /// the oldest rect with each color; rects with further colors are not highlighted.
+    pub fn highlight(&mut self, handle: &Handle, color: [f32; 4]) {
+        self.highlights.insert(handle.inner.id(), color);
+    }
+
+    pub fn unhighlight(&mut self, handle: &Handle) {
+        self.highlights.remove(&handle.inner.id());
+    }
+
+    pub fn clear_highlights(&mut self) {
+        self.highlights.clear();
+    }
+
     pub fn update_cluster_states(&mut self, simulation: &Simulation) {
+        // Rects changed since the last count were drawn with the previous states.
+        self.update_wire_counts();
+
         let mut state_buffer: BitVec<u32, Lsb0> =
             BitVec::with_capacity(simulation.num_clusters() as usize * 2);
         for index in 0..simulation.num_clusters() {
@@ -267,6 +709,25 @@ impl RectRenderer {
             0,
             bytemuck::cast_slice(state_buffer.as_raw_slice()),
         );
+        self.cluster_states = state_buffer;
+        self.wire_counts.recount_powered(&self.cluster_states);
+    }
+
+    /// The number of wire-colored rects, as of the last draw.
+    pub fn wire_count(&self) -> usize {
+        self.wire_counts.total
+    }
+
+    /// The number of wire-colored rects drawn in their powered color, as of the last draw.
+    pub fn wire_count_powered(&self) -> usize {
+        self.wire_counts.powered
+    }
+
+    /// Counts the wire-colored rects inserted, changed or removed since the last call.
+    fn update_wire_counts(&mut self) {
+        for change in self.instances.take_changes() {
+            self.wire_counts.apply(change, &self.cluster_states);
+        }
     }
 
     pub fn draw(
@@ -276,6 +737,160 @@ impl RectRenderer {
         frame_view: &wgpu::TextureView,
         depth_view: &wgpu::TextureView,
     ) {
+        self.draw_with_highlight(&HashSet::new(), viewport, encoder, frame_view, depth_view);
+    }
+
+    /// Draws every rect, then draws the highlighted ones again with the highlight color added.
+    pub fn draw_with_highlight(
+        &mut self,
+        highlighted: &HashSet<&Handle>,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        self.draw_inner(
+            highlighted,
+            None,
+            None,
+            viewport,
+            encoder,
+            frame_view,
+            depth_view,
+        );
+    }
+
+    /// Draws the `selected` rects enlarged by `outline_width` world units in `outline_color`,
+    /// then every rect normally on top, leaving a border around the selected ones.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_outlined(
+        &mut self,
+        selected: &HashSet<&Handle>,
+        outline_color: [f32; 4],
+        outline_width: f32,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let outline = Outline {
+            selected,
+            color: outline_color,
+            width: outline_width,
+        };
+        self.draw_inner(
+            &HashSet::new(),
+            Some(outline),
+            None,
+            viewport,
+            encoder,
+            frame_view,
+            depth_view,
+        );
+    }
+
+    /// Draws every wire as dashes of `dash_len` world units separated by gaps of `gap_len`,
+    /// measured along the wire, e.g. to single out clock nets. Other rects are drawn normally.
+    pub fn draw_dashed(
+        &mut self,
+        dash_len: f32,
+        gap_len: f32,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        self.draw_inner(
+            &HashSet::new(),
+            None,
+            Some(dash_uniform(dash_len, gap_len, false)),
+            viewport,
+            encoder,
+            frame_view,
+            depth_view,
+        );
+    }
+
+    /// Like [`Self::draw_dashed`], but dashes every rect along its longer side, e.g. to draw
+    /// dashed outlines.
+    pub fn draw_all_dashed(
+        &mut self,
+        dash_len: f32,
+        gap_len: f32,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        self.draw_inner(
+            &HashSet::new(),
+            None,
+            Some(dash_uniform(dash_len, gap_len, true)),
+            viewport,
+            encoder,
+            frame_view,
+            depth_view,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_inner(
+        &mut self,
+        highlighted: &HashSet<&Handle>,
+        outline: Option<Outline>,
+        dash: Option<[u32; 4]>,
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let outlined_indices = match &outline {
+            Some(outline) => {
+                let mut uniform = [0.0; 8];
+                uniform[..4].copy_from_slice(&outline.color);
+                uniform[4] = outline.width;
+                self.gfx.queue.write_buffer(
+                    &self.highlight_buffer,
+                    OUTLINE_SLOT * HIGHLIGHT_SLOT_STRIDE,
+                    bytemuck::bytes_of(&uniform),
+                );
+                self.instances
+                    .indices(outline.selected.iter().map(|handle| &handle.inner))
+            }
+            None => Vec::new(),
+        };
+        let dash_offset = match dash {
+            Some(dash) => {
+                self.gfx.queue.write_buffer(
+                    &self.dash_buffer,
+                    DASHED_SLOT * HIGHLIGHT_SLOT_STRIDE,
+                    bytemuck::bytes_of(&dash),
+                );
+                (DASHED_SLOT * HIGHLIGHT_SLOT_STRIDE) as u32
+            }
+            None => 0,
+        };
+        let highlighted_indices = self
+            .instances
+            .indices(highlighted.iter().map(|handle| &handle.inner));
+        // Forget the highlights of rects that were removed since the last draw.
+        let instances = &mut self.instances;
+        self.highlights.retain(|&id, _| instances.contains_id(id));
+        // Slot 0 holds the color of `highlighted`, the others one color of `self.highlights` each.
+        let persistent: Vec<(u32, Vec<u32>)> = highlight_slots(&self.highlights)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (color, ids))| {
+                let offset = (index as u64 + 1) * HIGHLIGHT_SLOT_STRIDE;
+                self.gfx.queue.write_buffer(
+                    &self.highlight_buffer,
+                    offset,
+                    bytemuck::bytes_of(&color),
+                );
+                (offset as u32, self.instances.indices_by_id(ids))
+            })
+            .collect();
+        self.update_wire_counts();
         let instance_count = self.instances.len();
         let instance_buffer = match self.instances.buffer() {
             Some(buffer) => buffer,
@@ -302,18 +917,122 @@ impl RectRenderer {
             }),
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         render_pass.set_bind_group(0, viewport.bind_group(), &[]);
-        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[dash_offset]);
+        if !outlined_indices.is_empty() {
+            render_pass.set_pipeline(&self.outline_pipeline);
+            let offset = (OUTLINE_SLOT * HIGHLIGHT_SLOT_STRIDE) as u32;
+            render_pass.set_bind_group(2, &self.highlight_bind_group, &[offset]);
+            for instances in contiguous_ranges(&outlined_indices) {
+                render_pass.draw_indexed(0..INDICES.len().try_into().unwrap(), 0, instances);
+            }
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
         render_pass.draw_indexed(
             0..INDICES.len().try_into().unwrap(),
             0,
             0..instance_count.try_into().expect("too many instances"),
         );
+
+        if highlighted_indices.is_empty() && persistent.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.highlight_pipeline);
+        for (offset, indices) in std::iter::once((0, highlighted_indices)).chain(persistent) {
+            render_pass.set_bind_group(2, &self.highlight_bind_group, &[offset]);
+            for instances in contiguous_ranges(&indices) {
+                render_pass.draw_indexed(0..INDICES.len().try_into().unwrap(), 0, instances);
+            }
+        }
+    }
+}
+
+const DEFAULT_HIGHLIGHT_COLOR: Vec4 = Vec4::new(0.2, 0.4, 0.8, 1.0);
+
+/// The number of highlight colors that can be drawn in one frame.
+pub const HIGHLIGHT_SLOTS: u64 = 8;
+/// The uniform slot after the highlight colors, holding the outline of [`RectRenderer::draw_outlined`].
+const OUTLINE_SLOT: u64 = HIGHLIGHT_SLOTS;
+/// Highlight colors are bound with dynamic offsets, which must be multiples of the device's
+/// uniform offset alignment (256 bytes by default).
+const HIGHLIGHT_SLOT_STRIDE: u64 = 256;
+/// The size of the `Highlight` shader struct: a color and the outline width, padded.
+const HIGHLIGHT_UNIFORM_SIZE: u64 = std::mem::size_of::<[f32; 8]>() as u64;
+
+/// The dash uniform slot used by [`RectRenderer::draw_dashed`] and
+/// [`RectRenderer::draw_all_dashed`]. It follows the solid slot and uses the same stride as the
+/// highlights.
+const DASHED_SLOT: u64 = 1;
+/// The size of the `Dash` shader struct: the dash and gap lengths and the rects to dash,
+/// padded.
+const DASH_UNIFORM_SIZE: u64 = std::mem::size_of::<[u32; 4]>() as u64;
+
+/// Groups persistent highlights by color, one group per highlight slot after the first, in the
+/// order of the lowest handle id with each color. Colors that do not fit are left out.
+fn highlight_slots(highlights: &BTreeMap<u64, [f32; 4]>) -> Vec<([f32; 4], Vec<u64>)> {
+    let mut slots: Vec<([f32; 4], Vec<u64>)> = Vec::new();
+    for (&id, &color) in highlights {
+        if let Some((_, ids)) = slots
+            .iter_mut()
+            .find(|(slot_color, _)| *slot_color == color)
+        {
+            ids.push(id);
+        } else if slots.len() + 1 < HIGHLIGHT_SLOTS as usize {
+            slots.push((color, vec![id]));
+        }
     }
+    slots
+}
+
+struct Outline<'a> {
+    selected: &'a HashSet<&'a Handle>,
+    color: [f32; 4],
+    width: f32,
+}
+
+fn create_render_pipeline(
+    gfx: &GraphicsContext,
+    layout: &wgpu::PipelineLayout,
+    shader_module: &wgpu::ShaderModule,
+    polygon_mode: wgpu::PolygonMode,
+) -> wgpu::RenderPipeline {
+    gfx.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("RectRenderer.render_pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: "vs_main",
+                buffers: &[Vertex::buffer_layout(), Instance::buffer_layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Cw,
+                polygon_mode,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: gfx.depth_format,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: gfx.render_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
 }
 
 #[derive(Default)]
@@ -322,6 +1041,111 @@ pub struct Rect {
     pub z_index: u8,
     pub size: Vec2,
     pub color: Color,
+    pub shape: Shape,
+}
+
+impl Rect {
+    /// The same rect extended by `margin` on every side, e.g. to make thin rects easier to hit
+    /// with the mouse.
+    pub fn grow(self, margin: f32) -> Rect {
+        Self {
+            position: self.position - Vec2::splat(margin),
+            size: self.size + Vec2::splat(2.0 * margin),
+            ..self
+        }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.position).all() && point.cmple(self.position + self.size).all()
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size * 0.5
+    }
+
+    /// The corners in counter-clockwise order, starting from the bottom left.
+    pub fn corners(&self) -> [Vec2; 4] {
+        let min = self.position;
+        let max = self.position + self.size;
+        [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+    }
+
+    /// Creates the marker for a pin of a component at `gate_position`, drawn in the tile
+    /// `pin_offset` away from it.
+    ///
+    /// Input pins are drawn as an arrow pointing into the component, output pins as a circle and
+    /// bidirectional pins as a square.
+    pub fn from_gate_pin(gate_position: IVec2, pin_offset: IVec2, kind: PinKind) -> Self {
+        let (radius, shape) = match kind {
+            PinKind::Input => (PIN_RADIUS, Shape::Arrow(direction_of(-pin_offset))),
+            PinKind::Output => (OUTPUT_RADIUS, Shape::Circle),
+            PinKind::Bidirectional => (PIN_RADIUS, Shape::Square),
+        };
+        Self {
+            position: (gate_position + pin_offset).as_vec2() + Vec2::splat(0.5 - radius),
+            z_index: PIN_Z_INDEX,
+            size: Vec2::splat(2.0 * radius),
+            color: Color::Fixed(Vec4::new(1.0, 1.0, 1.0, 1.0)),
+            shape,
+        }
+    }
+}
+
+/// The outline a rect is cut to by the fragment shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+    #[default]
+    Square,
+    Circle,
+    /// A triangle pointing in the given direction.
+    Arrow(Direction),
+}
+
+impl Shape {
+    fn index(&self) -> u32 {
+        match self {
+            Self::Square => 0,
+            Self::Circle => 1,
+            Self::Arrow(Direction::East) => 2,
+            Self::Arrow(Direction::North) => 3,
+            Self::Arrow(Direction::West) => 4,
+            Self::Arrow(Direction::South) => 5,
+        }
+    }
+}
+
+/// How a pin takes part in its net: input pins only observe it, output pins drive it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum PinKind {
+    Input,
+    Output,
+    #[default]
+    Bidirectional,
+}
+
+impl PinKind {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Input => Self::Output,
+            Self::Output => Self::Bidirectional,
+            Self::Bidirectional => Self::Input,
+        }
+    }
+}
+
+// The direction closest to the given offset, along its longest axis.
+fn direction_of(offset: IVec2) -> Direction {
+    if offset.x.abs() >= offset.y.abs() {
+        if offset.x >= 0 {
+            Direction::East
+        } else {
+            Direction::West
+        }
+    } else if offset.y > 0 {
+        Direction::North
+    } else {
+        Direction::South
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -440,6 +1264,7 @@ impl From<Wire> for Rect {
             size: abs_size.as_vec2() + Vec2::splat(2.0 * WIRE_RADIUS)
                 - Vec2::splat(start_conn.offset() + end_conn.offset()) * axis,
             color: wire.color,
+            shape: Shape::Square,
         }
     }
 }
@@ -455,6 +1280,7 @@ impl From<Body> for Rect {
             z_index: BODY_Z_INDEX,
             size: Vec2::splat(2.0 * BODY_RADIUS),
             color: Color::Fixed(Vec4::new(1.0, 1.0, 1.0, 1.0)),
+            shape: Shape::Square,
         }
     }
 }
@@ -471,6 +1297,7 @@ impl From<Pin> for Rect {
             z_index: PIN_Z_INDEX,
             size: Vec2::splat(2.0 * PIN_RADIUS),
             color: pin.color,
+            shape: Shape::Square,
         }
     }
 }
@@ -492,6 +1319,7 @@ impl From<SidePin> for Rect {
             size: transform * Vec2::new(SIDE_PIN_HEIGHT, 2.0 * PIN_RADIUS),
             z_index: SIDE_PIN_Z_INDEX,
             color: pin.color,
+            shape: Shape::Square,
         }
     }
 }
@@ -513,6 +1341,7 @@ impl From<Output> for Rect {
             size: transform * Vec2::new(OUTPUT_HEIGHT, 2.0 * OUTPUT_RADIUS),
             z_index: OUTPUT_Z_INDEX,
             color: output.color,
+            shape: Shape::Square,
         }
     }
 }
@@ -528,6 +1357,66 @@ impl From<Crossover> for Rect {
             z_index: CROSSOVER_Z_INDEX,
             size: Vec2::splat(2.0 * CROSSOVER_RADIUS),
             color: Color::Fixed(Vec4::new(0.5, 0.5, 0.5, 1.0)),
+            shape: Shape::Square,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight_slots, Instance, WireCounts, HIGHLIGHT_SLOTS};
+    use crate::instance::Change;
+    use bitvec::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn wire(cluster_index: u32) -> Instance {
+        Instance {
+            position: [0.0; 2],
+            z_index: 0.0,
+            size: [1.0; 2],
+            color: [1.0; 4],
+            cluster_index,
+            shape: 0,
         }
     }
+
+    #[test]
+    fn wire_counts_follow_changes_in_order() {
+        // Cluster 2 is powered, see `is_powered`.
+        let cluster_states = bitvec![u32, Lsb0; 0, 1];
+        let mut counts = WireCounts::default();
+
+        // A wire inserted under a new cluster, merged into another and removed in one batch.
+        for change in [
+            Change::Added(wire(4)),
+            Change::Removed(wire(4)),
+            Change::Added(wire(2)),
+            Change::Added(wire(6)),
+            Change::Removed(wire(2)),
+        ] {
+            counts.apply(change, &cluster_states);
+        }
+        assert_eq!((counts.total, counts.powered), (1, 0));
+        assert_eq!(counts.clusters.get(&6), Some(&1));
+        assert_eq!(counts.clusters.len(), 1);
+    }
+
+    #[test]
+    fn highlight_slots_follow_handle_order() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let highlights = BTreeMap::from([(7, red), (3, blue), (5, red), (9, blue)]);
+        assert_eq!(
+            highlight_slots(&highlights),
+            vec![(blue, vec![3, 9]), (red, vec![5, 7])]
+        );
+
+        // Slot 0 is reserved for transient highlights.
+        let many: BTreeMap<u64, [f32; 4]> = (0..HIGHLIGHT_SLOTS)
+            .map(|id| (id, [id as f32; 4]))
+            .collect();
+        let slots = highlight_slots(&many);
+        assert_eq!(slots.len(), HIGHLIGHT_SLOTS as usize - 1);
+        assert_eq!(slots.last().unwrap().1, vec![HIGHLIGHT_SLOTS - 2]);
+    }
 }
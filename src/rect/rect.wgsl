@@ -5,11 +5,19 @@ struct VertexInput {
     @location(3) size: vec2<f32>,
     @location(4) color: vec4<f32>,
     @location(5) cluster_index: u32,
+    @location(6) shape: u32,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) end_color: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) gradient_axis: vec2<f32>,
+    @location(4) @interpolate(flat) shape: u32,
+    @location(5) world_position: vec2<f32>,
+    // 1 for wire-colored rects, the only ones dashed unless `dash.all_rects` is set.
+    @location(6) @interpolate(flat) is_wire: u32,
 };
 
 struct Viewport {
@@ -24,18 +32,47 @@ struct ClusterStates {
 @group(1) @binding(0) var<uniform> cluster_states: ClusterStates;
 struct WirePalette {
     buffer: array<vec4<f32>, 2>,
+    end_buffer: array<vec4<f32>, 2>,
+    gradient: u32,
 };
 @group(1) @binding(1) var<uniform> wire_palette: WirePalette;
+struct AlphaScale {
+    alpha_scale: f32,
+};
+@group(1) @binding(2) var<uniform> alpha_scale: AlphaScale;
+struct Dash {
+    // Lengths in world units. Rects are drawn solid if `dash_len` is 0.
+    dash_len: f32,
+    gap_len: f32,
+    // 1 to dash every rect, 0 for wires only.
+    all_rects: u32,
+};
+@group(1) @binding(3) var<uniform> dash: Dash;
+struct Highlight {
+    color: vec4<f32>,
+    // How far outlines extend past their rect, in world units.
+    outline_width: f32,
+};
+@group(2) @binding(0) var<uniform> highlight: Highlight;
 
-@vertex
-fn vs_main(in: VertexInput) -> VertexOutput {
+// Transforms a vertex of the rect extended by `grow` on every side.
+fn transform_vertex(in: VertexInput, grow: f32) -> VertexOutput {
     var out: VertexOutput;
 
-    let rect_coordinate: vec2<f32> = in.rect_position + in.size * in.position;
+    let rect_coordinate: vec2<f32> = in.rect_position - grow + (in.size + 2.0 * grow) * in.position;
     out.position = viewport.view_proj * vec4<f32>(rect_coordinate, in.z_index, 1.0);
+    out.world_position = rect_coordinate;
+
+    out.uv = in.position;
+    out.shape = in.shape;
+    // Blend along the longer side of the rect, so wires fade along their length.
+    let abs_size: vec2<f32> = abs(in.size);
+    out.gradient_axis = select(vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), abs_size.x >= abs_size.y);
 
+    out.is_wire = u32(in.cluster_index != 0xffffffffu);
     if (in.cluster_index == 0xffffffffu) {
         out.color = in.color;
+        out.end_color = in.color;
     } else {
         let array_index: u32 = in.cluster_index >> 8u;
         let component_index: u32 = (in.cluster_index >> 6u) & 3u;
@@ -44,12 +81,69 @@ fn vs_main(in: VertexInput) -> VertexOutput {
         let invert: u32 = in.cluster_index & 1u;
 
         out.color = wire_palette.buffer[is_on ^ invert];
+        if (wire_palette.gradient != 0u) {
+            out.end_color = wire_palette.end_buffer[is_on ^ invert];
+        } else {
+            out.end_color = out.color;
+        }
     }
     
     return out;
 }
 
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    return transform_vertex(in, 0.0);
+}
+
+@vertex
+fn vs_outline(in: VertexInput) -> VertexOutput {
+    return transform_vertex(in, highlight.outline_width);
+}
+
+// Whether the point, in [-1, 1] rect coordinates, lies outside of the shape.
+fn outside_shape(shape: u32, p: vec2<f32>) -> bool {
+    if (shape == 1u) {
+        return length(p) > 1.0;
+    }
+    if (shape >= 2u) {
+        // Rotate the point so the arrow points east.
+        var q: vec2<f32> = p;
+        switch (shape) {
+            case 3u: { q = vec2<f32>(p.y, -p.x); }
+            case 4u: { q = -p; }
+            case 5u: { q = vec2<f32>(-p.y, p.x); }
+            default: {}
+        }
+        return abs(q.y) > (1.0 - q.x) * 0.5;
+    }
+    return false;
+}
+
+// Whether the fragment falls in a gap of the dash pattern. The pattern is measured in world
+// space along the rect, so dashes line up across wires on the same axis.
+fn in_dash_gap(in: VertexOutput) -> bool {
+    if ((in.is_wire == 0u && dash.all_rects == 0u) || dash.dash_len <= 0.0) {
+        return false;
+    }
+    let period: f32 = dash.dash_len + dash.gap_len;
+    let distance: f32 = dot(in.world_position, in.gradient_axis);
+    return distance - floor(distance / period) * period >= dash.dash_len;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    if (outside_shape(in.shape, in.uv * 2.0 - 1.0) || in_dash_gap(in)) {
+        discard;
+    }
+    let color: vec4<f32> = mix(in.color, in.end_color, dot(in.uv, in.gradient_axis));
+    return vec4<f32>(color.rgb, color.a * alpha_scale.alpha_scale);
+}
+
+@fragment
+fn fs_highlight(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (outside_shape(in.shape, in.uv * 2.0 - 1.0) || in_dash_gap(in)) {
+        discard;
+    }
+    return vec4<f32>(highlight.color.rgb, highlight.color.a * alpha_scale.alpha_scale);
 }
@@ -0,0 +1,164 @@
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use glam::{Vec2, Vec4};
+use once_cell::sync::Lazy;
+use wgpu::util::DeviceExt;
+
+/// Per-instance data: bar position and size in pixels, then color.
+pub type Bar = [f32; 8];
+
+static VERTEX_ATTRIBUTES: Lazy<[wgpu::VertexAttribute; 1]> = Lazy::new(|| {
+    wgpu::vertex_attr_array![
+        0 => Float32x2,
+    ]
+});
+
+static INSTANCE_ATTRIBUTES: Lazy<[wgpu::VertexAttribute; 3]> = Lazy::new(|| {
+    wgpu::vertex_attr_array![
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x4,
+    ]
+});
+
+const VERTICES: &[[f32; 2]] = &[[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]];
+
+const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+/// A bar with its top-left corner at `position`, in window pixels with Y pointing down.
+pub fn bar(position: Vec2, size: Vec2, color: Vec4) -> Bar {
+    [
+        position.x, position.y, size.x, size.y, color.x, color.y, color.z, color.w,
+    ]
+}
+
+/// Draws flat colored rectangles in window pixels, over whatever is already in the frame.
+pub struct ScreenBars {
+    gfx: GraphicsContext,
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl ScreenBars {
+    /// Creates a renderer for up to `capacity` bars per draw.
+    pub fn new(gfx: &GraphicsContext, viewport: &Viewport, capacity: usize) -> Self {
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ScreenBars.pipeline_layout"),
+                bind_group_layouts: &[viewport.bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+        let shader_module = gfx
+            .device
+            .create_shader_module(wgpu::include_wgsl!("screen_bars.wgsl"));
+        let render_pipeline = gfx
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ScreenBars.render_pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: "vs_main",
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<[f32; 2]>().try_into().unwrap(),
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &VERTEX_ATTRIBUTES[..],
+                        },
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<Bar>().try_into().unwrap(),
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &INSTANCE_ATTRIBUTES[..],
+                        },
+                    ],
+                },
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gfx.render_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+        let vertex_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ScreenBars.vertex_buffer"),
+                contents: bytemuck::cast_slice(VERTICES),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let index_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ScreenBars.index_buffer"),
+                contents: bytemuck::cast_slice(INDICES),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+        let instance_buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ScreenBars.instance_buffer"),
+            size: (std::mem::size_of::<Bar>() * capacity).try_into().unwrap(),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            gfx: gfx.clone(),
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            capacity,
+        }
+    }
+
+    /// Draws `bars` in order, later ones on top. Bars past the capacity are dropped.
+    pub fn draw(
+        &mut self,
+        bars: &[Bar],
+        viewport: &Viewport,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+    ) {
+        let bars = &bars[..bars.len().min(self.capacity)];
+        if bars.is_empty() {
+            return;
+        }
+        self.gfx
+            .queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(bars));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ScreenBars.render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_bind_group(0, viewport.bind_group(), &[]);
+        render_pass.draw_indexed(
+            0..INDICES.len().try_into().unwrap(),
+            0,
+            0..bars.len().try_into().unwrap(),
+        );
+    }
+}
@@ -0,0 +1,35 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) bar_position: vec2<f32>,
+    @location(2) bar_size: vec2<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+struct Viewport {
+    view_proj: mat4x4<f32>,
+    view_size: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> viewport: Viewport;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Bars are laid out in window pixels, with Y pointing down.
+    let pixel: vec2<f32> = in.bar_position + in.bar_size * in.position;
+    let ndc: vec2<f32> = pixel / viewport.view_size * 2.0 - 1.0;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.color = in.color;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,82 @@
+use crate::serializer::RectI;
+use glam::IVec2;
+use std::collections::HashSet;
+
+/// A set of selected tiles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    positions: HashSet<IVec2>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn contains(&self, position: IVec2) -> bool {
+        self.positions.contains(&position)
+    }
+
+    /// Adds `position` to the selection. Returns false if it was already selected.
+    pub fn insert(&mut self, position: IVec2) -> bool {
+        self.positions.insert(position)
+    }
+
+    /// Removes `position` from the selection. Returns false if it was not selected.
+    pub fn remove(&mut self, position: IVec2) -> bool {
+        self.positions.remove(&position)
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.positions.iter().copied()
+    }
+
+    /// The smallest rectangle containing every selected tile.
+    pub fn bounds(&self) -> Option<RectI> {
+        let min = self.iter().reduce(IVec2::min)?;
+        let max = self.iter().reduce(IVec2::max)?;
+        Some(RectI {
+            min,
+            max: max + IVec2::ONE,
+        })
+    }
+}
+
+impl FromIterator<IVec2> for Selection {
+    fn from_iter<I: IntoIterator<Item = IVec2>>(iter: I) -> Self {
+        Self {
+            positions: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Selection;
+    use glam::IVec2;
+
+    #[test]
+    fn bounds() {
+        let mut selection = Selection::new();
+        assert_eq!(selection.bounds(), None);
+
+        selection.insert(IVec2::new(2, -1));
+        selection.insert(IVec2::new(-3, 4));
+        let bounds = selection.bounds().unwrap();
+        assert_eq!(bounds.min, IVec2::new(-3, -1));
+        assert_eq!(bounds.max, IVec2::new(3, 5));
+        assert!(bounds.contains(IVec2::new(2, 4)));
+    }
+}
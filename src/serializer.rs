@@ -0,0 +1,391 @@
+use crate::board::Board;
+use crate::circuit::{Circuit, ComponentType};
+use crate::direction::Direction;
+use crate::rect::PinKind;
+use anyhow::{ensure, Context};
+use glam::IVec2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File extension for saved circuits. Either format may be stored under it.
+pub const EXTENSION: &str = "flipflop";
+
+/// The version of [`CircuitData`] written by this build. Files saved before the version was
+/// recorded are read as version 1.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A plain snapshot of a circuit's layout, independent of any GPU state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitData {
+    #[serde(default = "first_format")]
+    pub format: u32,
+    pub components: Vec<ComponentRecord>,
+    pub wires: Vec<WireRecord>,
+    /// Every board except the root board, in draw order.
+    #[serde(default)]
+    pub boards: Vec<Board>,
+}
+
+fn first_format() -> u32 {
+    1
+}
+
+impl Default for CircuitData {
+    fn default() -> Self {
+        Self {
+            format: FORMAT_VERSION,
+            components: Vec::new(),
+            wires: Vec::new(),
+            boards: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentRecord {
+    pub ty: ComponentType,
+    pub position: IVec2,
+    pub orientation: Direction,
+    #[serde(default)]
+    pub switched: bool,
+    #[serde(default)]
+    pub pin_kind: PinKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireRecord {
+    pub start: IVec2,
+    pub end: IVec2,
+}
+
+impl WireRecord {
+    /// Parses a JSON array of `{"start": [x, y], "end": [x, y]}` objects.
+    ///
+    /// Fails only if the text is not a JSON array; malformed entries get their own error, so the
+    /// rest of the list can still be used.
+    pub fn list_from_json(json: &str) -> anyhow::Result<Vec<anyhow::Result<Self>>> {
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_str(json).context("Expected a JSON array of wires")?;
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                serde_json::from_value(entry).with_context(|| format!("Malformed wire #{index}"))
+            })
+            .collect())
+    }
+}
+
+/// An axis-aligned rectangle of tiles, from `min` (inclusive) to `max` (exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RectI {
+    pub min: IVec2,
+    pub max: IVec2,
+}
+
+impl RectI {
+    pub fn contains(&self, position: IVec2) -> bool {
+        position.cmpge(self.min).all() && position.cmplt(self.max).all()
+    }
+
+    /// Every tile inside the rectangle, row by row.
+    pub fn tiles(&self) -> impl Iterator<Item = IVec2> {
+        let Self { min, max } = *self;
+        (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| IVec2::new(x, y)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Ron,
+}
+
+impl Format {
+    /// Guesses the format from the content: JSON documents start with an object or array,
+    /// anything else is treated as RON.
+    pub fn detect(content: &str) -> Self {
+        match content.trim_start().chars().next() {
+            Some('{' | '[') => Self::Json,
+            _ => Self::Ron,
+        }
+    }
+}
+
+impl CircuitData {
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.wires.is_empty()
+    }
+
+    /// The components and wires inside `region`, relative to `region.min`.
+    ///
+    /// Wires are only included if they lie entirely inside the region. Boards are left out.
+    pub fn region(&self, region: RectI) -> Self {
+        Self {
+            format: self.format,
+            components: self
+                .components
+                .iter()
+                .filter(|component| region.contains(component.position))
+                .copied()
+                .collect(),
+            wires: self
+                .wires
+                .iter()
+                .filter(|wire| region.contains(wire.start) && region.contains(wire.end))
+                .copied()
+                .collect(),
+            boards: Vec::new(),
+        }
+        .offset(-region.min)
+    }
+
+    /// Moves every component, wire and board by `offset`.
+    pub fn offset(&self, offset: IVec2) -> Self {
+        Self {
+            format: self.format,
+            components: self
+                .components
+                .iter()
+                .map(|&component| ComponentRecord {
+                    position: component.position + offset,
+                    ..component
+                })
+                .collect(),
+            wires: self
+                .wires
+                .iter()
+                .map(|wire| WireRecord {
+                    start: wire.start + offset,
+                    end: wire.end + offset,
+                })
+                .collect(),
+            boards: self
+                .boards
+                .iter()
+                .map(|&board| Board {
+                    position: board.position + offset,
+                    ..board
+                })
+                .collect(),
+        }
+    }
+
+    /// Fails if the data was written in a format this build does not know.
+    fn check_format(self) -> anyhow::Result<Self> {
+        ensure!(
+            self.format == FORMAT_VERSION,
+            "Unsupported circuit format {}, this version reads format {}",
+            self.format,
+            FORMAT_VERSION
+        );
+        Ok(self)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize circuit to JSON")
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str::<Self>(json)
+            .context("Failed to parse JSON circuit")?
+            .check_format()
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .context("Failed to serialize circuit to RON")
+    }
+
+    pub fn from_ron(ron: &str) -> anyhow::Result<Self> {
+        ron::from_str::<Self>(ron)
+            .context("Failed to parse RON circuit")?
+            .check_format()
+    }
+
+    /// Parses a circuit in either format, see [`Format::detect`].
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        match Format::detect(content) {
+            Format::Json => Self::from_json(content),
+            Format::Ron => Self::from_ron(content),
+        }
+    }
+}
+
+/// Saves and loads whole circuits.
+pub struct CircuitSerializer;
+
+impl CircuitSerializer {
+    pub fn to_json(circuit: &Circuit) -> anyhow::Result<String> {
+        circuit.to_data().to_json()
+    }
+
+    pub fn from_json(circuit: &mut Circuit, json: &str) -> anyhow::Result<()> {
+        circuit.load_data(&CircuitData::from_json(json)?);
+        Ok(())
+    }
+
+    pub fn to_ron(circuit: &Circuit) -> anyhow::Result<String> {
+        circuit.to_data().to_ron()
+    }
+
+    pub fn from_ron(circuit: &mut Circuit, ron: &str) -> anyhow::Result<()> {
+        circuit.load_data(&CircuitData::from_ron(ron)?);
+        Ok(())
+    }
+
+    /// Writes the circuit to `path` as RON, and marks it as saved.
+    pub fn save<P: AsRef<Path>>(circuit: &mut Circuit, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let ron = Self::to_ron(circuit)?;
+        std::fs::write(path, ron)
+            .with_context(|| format!("Failed to write circuit {}", path.display()))?;
+        circuit.mark_saved();
+        Ok(())
+    }
+
+    /// Replaces the circuit with the one stored at `path`, in either JSON or RON.
+    pub fn load<P: AsRef<Path>>(circuit: &mut Circuit, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read circuit {}", path.display()))?;
+        let data = CircuitData::parse(&content)
+            .with_context(|| format!("Invalid circuit {}", path.display()))?;
+        circuit.load_data(&data);
+        circuit.mark_saved();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitData, ComponentRecord, Format, RectI, WireRecord, FORMAT_VERSION};
+    use crate::board::Board;
+    use crate::circuit::ComponentType;
+    use crate::direction::Direction;
+    use crate::rect::PinKind;
+    use glam::IVec2;
+
+    fn sample() -> CircuitData {
+        CircuitData {
+            components: vec![
+                ComponentRecord {
+                    ty: ComponentType::Switch,
+                    position: IVec2::new(0, 0),
+                    orientation: Direction::East,
+                    switched: true,
+                    pin_kind: PinKind::Bidirectional,
+                },
+                ComponentRecord {
+                    ty: ComponentType::Flop,
+                    position: IVec2::new(4, 0),
+                    orientation: Direction::West,
+                    switched: false,
+                    pin_kind: PinKind::Bidirectional,
+                },
+                ComponentRecord {
+                    ty: ComponentType::Pin,
+                    position: IVec2::new(2, 0),
+                    orientation: Direction::East,
+                    switched: false,
+                    pin_kind: PinKind::Output,
+                },
+            ],
+            wires: vec![WireRecord {
+                start: IVec2::new(0, 0),
+                end: IVec2::new(4, 0),
+            }],
+            boards: vec![Board::new(IVec2::new(-1, -1), IVec2::new(7, 3)).with_color([0.5; 4])],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let data = sample();
+        let ron = data.to_ron().unwrap();
+        assert_eq!(Format::detect(&ron), Format::Ron);
+        assert_eq!(CircuitData::from_ron(&ron).unwrap(), data);
+        assert_eq!(CircuitData::parse(&ron).unwrap(), data);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let data = sample();
+        let json = data.to_json().unwrap();
+        assert_eq!(Format::detect(&json), Format::Json);
+        assert_eq!(CircuitData::from_json(&json).unwrap(), data);
+        assert_eq!(CircuitData::parse(&json).unwrap(), data);
+    }
+
+    #[test]
+    fn format_version() {
+        let json = sample().to_json().unwrap();
+        assert!(json.contains(&format!("\"format\": {FORMAT_VERSION}")));
+
+        // Files from before the version was recorded have no boards either.
+        let unversioned = r#"{"components": [], "wires": []}"#;
+        let data = CircuitData::from_json(unversioned).unwrap();
+        assert_eq!(data.format, 1);
+        assert!(data.boards.is_empty());
+
+        let future = format!(
+            r#"{{"format": {}, "components": [], "wires": []}}"#,
+            FORMAT_VERSION + 1
+        );
+        let error = CircuitData::parse(&future).unwrap_err();
+        assert!(error.to_string().contains("Unsupported circuit format"));
+    }
+
+    #[test]
+    fn ron_enum_syntax() {
+        let data = CircuitData::parse(
+            "(
+                components: [(ty: Pin, position: (1, 2), orientation: North)],
+                wires: [],
+            )",
+        )
+        .unwrap();
+        assert_eq!(data.components[0].ty, ComponentType::Pin);
+        assert!(!data.components[0].switched);
+        assert_eq!(data.components[0].pin_kind, PinKind::Bidirectional);
+    }
+
+    #[test]
+    fn wire_list_from_json() {
+        let wires = WireRecord::list_from_json(
+            r#"[{"start": [0, 0], "end": [0, 3]}, {"start": [1]}, {"start": [2, 2], "end": [5, 2]}]"#,
+        )
+        .unwrap();
+        assert_eq!(wires.len(), 3);
+        assert_eq!(
+            *wires[0].as_ref().unwrap(),
+            WireRecord {
+                start: IVec2::new(0, 0),
+                end: IVec2::new(0, 3),
+            }
+        );
+        assert!(wires[1].is_err());
+        assert_eq!(wires[2].as_ref().unwrap().end, IVec2::new(5, 2));
+
+        assert!(WireRecord::list_from_json(r#"{"start": [0, 0]}"#).is_err());
+    }
+
+    #[test]
+    fn region_round_trip() {
+        let data = sample();
+        let region = RectI {
+            min: IVec2::new(1, -1),
+            max: IVec2::new(5, 1),
+        };
+        let partial = data.region(region);
+        assert_eq!(partial.components.len(), 2);
+        assert!(partial.wires.is_empty());
+        assert_eq!(partial.components[1].position, IVec2::new(1, 1));
+
+        let restored = CircuitData::from_ron(&partial.to_ron().unwrap())
+            .unwrap()
+            .offset(region.min);
+        assert_eq!(restored.components, data.components[1..]);
+    }
+}
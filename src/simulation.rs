@@ -15,6 +15,10 @@ pub struct Simulation {
     flops: Vec<HashMap<u32, u32>>,
 
     manual_power: Vec<u32>,
+
+    // Cleared once a tick produces the same state as the previous one, since every following tick
+    // would too. Any change to the circuit or its inputs sets it again.
+    needs_tick: bool,
 }
 
 impl Simulation {
@@ -27,6 +31,7 @@ impl Simulation {
             flips: Vec::new(),
             flops: Vec::new(),
             manual_power: Vec::new(),
+            needs_tick: true,
         }
     }
 
@@ -36,6 +41,7 @@ impl Simulation {
 
     /// Allocates a new cluster ID that is not currently being used.
     pub fn alloc_cluster(&mut self) -> u32 {
+        self.needs_tick = true;
         if let Some(id) = self.free_clusters.pop() {
             id
         } else {
@@ -54,6 +60,7 @@ impl Simulation {
 
     /// Frees the given cluster, allowing the ID to be re-used.
     pub fn free_cluster(&mut self, id: u32) {
+        self.needs_tick = true;
         let index = cluster_array_index(id);
         assert!(self.flips[index].is_empty());
         assert!(self.flops[index].is_empty());
@@ -62,16 +69,19 @@ impl Simulation {
     }
 
     pub fn add_flip(&mut self, inp: u32, out: u32) {
+        self.needs_tick = true;
         let out = cluster_array_index(out);
         *self.flips[out].entry(inp).or_insert(0) += 1;
     }
 
     pub fn add_flop(&mut self, inp: u32, out: u32) {
+        self.needs_tick = true;
         let out = cluster_array_index(out);
         *self.flops[out].entry(inp).or_insert(0) += 1;
     }
 
     pub fn remove_flip(&mut self, inp: u32, out: u32) {
+        self.needs_tick = true;
         let out = cluster_array_index(out);
         let count = self.flips[out].get_mut(&inp).unwrap();
         *count -= 1;
@@ -81,6 +91,7 @@ impl Simulation {
     }
 
     pub fn remove_flop(&mut self, inp: u32, out: u32) {
+        self.needs_tick = true;
         let out = cluster_array_index(out);
         let count = self.flops[out].get_mut(&inp).unwrap();
         *count -= 1;
@@ -90,11 +101,13 @@ impl Simulation {
     }
 
     pub fn power(&mut self, id: u32) {
+        self.needs_tick = true;
         let id = cluster_array_index(id);
         self.manual_power[id] += 1;
     }
 
     pub fn unpower(&mut self, id: u32) {
+        self.needs_tick = true;
         let id = cluster_array_index(id);
         self.manual_power[id] -= 1;
     }
@@ -110,11 +123,21 @@ impl Simulation {
     }
 
     pub fn set_powered(&mut self, id: u32, powered: bool) {
+        self.needs_tick = true;
         let id = cluster_array_index(id);
         self.is_powered[id] = powered;
     }
 
-    pub fn tick(&mut self) {
+    /// Whether the state has reached a fixed point, so ticking would not change anything.
+    pub fn is_stable(&self) -> bool {
+        !self.needs_tick
+    }
+
+    /// Advances the simulation by one tick. Returns the number of clusters whose power changed.
+    pub fn tick(&mut self) -> usize {
+        if !self.needs_tick {
+            return 0;
+        }
         std::mem::swap(&mut self.is_powered, &mut self.was_powered);
 
         for i in 0..self.num_clusters {
@@ -123,6 +146,83 @@ impl Simulation {
                 || self.flips[i].iter().any(|(&id, _)| !self.was_powered(id))
                 || self.flops[i].iter().any(|(&id, _)| self.was_powered(id));
         }
+        let changed = self
+            .is_powered
+            .iter()
+            .zip(&self.was_powered)
+            .filter(|(is, was)| is != was)
+            .count();
+        if changed == 0 {
+            self.needs_tick = false;
+        }
+        changed
+    }
+
+    /// Powers each of `clusters` whose bit is set in `pattern`, bit 0 for the first cluster, for
+    /// `ticks` ticks, then releases them.
+    ///
+    /// Clusters with a clear bit, or past the 32nd, are left to their drivers. Calling this
+    /// repeatedly steps through a sequence of test inputs.
+    pub fn inject_bit_pattern(&mut self, clusters: &[u32], pattern: u32, ticks: usize) {
+        let driven: Vec<u32> = clusters
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| pattern.checked_shr(bit as u32).unwrap_or(0) & 1 != 0)
+            .map(|(_, &id)| id)
+            .collect();
+        for &id in &driven {
+            self.power(id);
+        }
+        for _ in 0..ticks {
+            self.tick();
+        }
+        for &id in &driven {
+            self.unpower(id);
+        }
+    }
+
+    /// Computes the steady state of an acyclic circuit in a single pass, evaluating each cluster
+    /// after the clusters feeding it instead of ticking until the state stops changing.
+    ///
+    /// Returns false, leaving the state untouched, if the clusters feed each other in a cycle.
+    pub fn settle_acyclic(&mut self) -> bool {
+        let cluster_count = cluster_array_index(self.num_clusters);
+        let mut state: Vec<Option<bool>> = vec![None; cluster_count];
+        let mut visiting = vec![false; cluster_count];
+        for root in 0..cluster_count {
+            let mut stack = vec![root];
+            while let Some(&cluster) = stack.last() {
+                if state[cluster].is_some() {
+                    stack.pop();
+                    continue;
+                }
+                let pending: Vec<usize> = self.flips[cluster]
+                    .keys()
+                    .chain(self.flops[cluster].keys())
+                    .map(|&id| cluster_array_index(id))
+                    .filter(|&id| state[id].is_none())
+                    .collect();
+                if pending.is_empty() {
+                    let powered = |id: &u32| state[cluster_array_index(*id)] == Some(true);
+                    state[cluster] = Some(
+                        self.manual_power[cluster] > 0
+                            || self.flips[cluster].keys().any(|id| !powered(id))
+                            || self.flops[cluster].keys().any(powered),
+                    );
+                    visiting[cluster] = false;
+                    stack.pop();
+                } else if pending.iter().any(|&id| visiting[id]) {
+                    return false;
+                } else {
+                    visiting[cluster] = true;
+                    stack.extend(pending);
+                }
+            }
+        }
+        self.is_powered = state.into_iter().map(Option::unwrap_or_default).collect();
+        self.was_powered = self.is_powered.clone();
+        self.needs_tick = false;
+        true
     }
 }
 
@@ -153,6 +253,77 @@ mod tests {
         sim.free_cluster(cluster);
     }
 
+    #[test]
+    fn tick_counts_changes() {
+        let mut sim = Simulation::new();
+
+        let input = sim.alloc_cluster();
+        let output = sim.alloc_cluster();
+        sim.add_flip(input, output);
+        assert_eq!(sim.tick(), 1);
+        assert_eq!(sim.tick(), 0);
+        assert!(sim.is_stable());
+
+        sim.power(input);
+        assert_eq!(sim.tick(), 1);
+        assert_eq!(sim.tick(), 1);
+        assert_eq!(sim.tick(), 0);
+        assert!(!sim.is_powered(output));
+    }
+
+    #[test]
+    fn inject_bit_pattern() {
+        let mut sim = Simulation::new();
+
+        let a = sim.alloc_cluster();
+        let b = sim.alloc_cluster();
+        let out = sim.alloc_cluster();
+        sim.add_flop(b, out);
+        sim.inject_bit_pattern(&[a, b], 0b10, 2);
+        assert!(!sim.is_powered(a));
+        assert!(sim.is_powered(b));
+        assert!(sim.is_powered(out));
+
+        // Released afterwards, so the next ticks recompute them from their drivers.
+        sim.tick();
+        assert!(!sim.is_powered(b));
+        sim.tick();
+        assert!(!sim.is_powered(out));
+    }
+
+    #[test]
+    fn stable_skips_ticks() {
+        let mut sim = Simulation::new();
+
+        let a = sim.alloc_cluster();
+        let b = sim.alloc_cluster();
+        sim.add_flop(a, b);
+        sim.tick();
+        assert!(sim.is_stable());
+
+        sim.power(a);
+        assert!(!sim.is_stable());
+        sim.tick();
+        assert!(sim.is_powered(a));
+        assert!(!sim.is_powered(b));
+        sim.tick();
+        assert!(sim.is_powered(b));
+        sim.tick();
+        assert!(sim.is_stable());
+        assert!(sim.was_powered(b));
+
+        sim.unpower(a);
+        sim.tick();
+        sim.tick();
+        sim.tick();
+        assert!(sim.is_stable());
+        assert!(!sim.is_powered(b));
+
+        sim.remove_flop(a, b);
+        sim.free_cluster(a);
+        sim.free_cluster(b);
+    }
+
     #[test]
     fn sr_latch_astable() {
         let mut sim = Simulation::new();
@@ -246,4 +417,32 @@ mod tests {
         sim.free_cluster(a);
         sim.free_cluster(b);
     }
+
+    #[test]
+    fn settle_acyclic() {
+        let mut sim = Simulation::new();
+
+        let a = sim.alloc_cluster();
+        let b = sim.alloc_cluster();
+        let c = sim.alloc_cluster();
+        // Added out of order, so evaluating by cluster index alone would be wrong.
+        sim.add_flop(b, c);
+        sim.add_flip(a, b);
+        sim.power(a);
+        assert!(sim.settle_acyclic());
+        assert!(sim.is_stable());
+        assert!(sim.is_powered(a));
+        assert!(!sim.is_powered(b));
+        assert!(!sim.is_powered(c));
+
+        sim.unpower(a);
+        assert!(sim.settle_acyclic());
+        assert!(sim.is_powered(b));
+        assert!(sim.is_powered(c));
+        assert!(sim.was_powered(c));
+
+        sim.add_flip(c, a);
+        assert!(!sim.settle_acyclic());
+        assert!(!sim.is_stable());
+    }
 }
@@ -0,0 +1,256 @@
+//! Two-level logic minimization for building circuits from truth tables.
+
+use anyhow::ensure;
+
+/// The largest number of inputs [`minimize`] is meant for.
+pub const MAX_INPUTS: usize = 6;
+
+/// The outputs of a combinational circuit for every combination of its inputs.
+///
+/// Rows are indexed by the input values read as a binary number, input 0 being the least
+/// significant bit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    input_count: usize,
+    output_count: usize,
+    rows: Vec<Vec<bool>>,
+}
+
+impl TruthTable {
+    /// Builds a table from its rows, each holding one value per output.
+    pub fn new(input_count: usize, rows: Vec<Vec<bool>>) -> anyhow::Result<Self> {
+        ensure!(
+            input_count <= MAX_INPUTS,
+            "Truth tables are limited to {} inputs, got {}",
+            MAX_INPUTS,
+            input_count
+        );
+        ensure!(
+            rows.len() == 1 << input_count,
+            "A truth table with {} inputs needs {} rows, got {}",
+            input_count,
+            1 << input_count,
+            rows.len()
+        );
+        let output_count = rows[0].len();
+        ensure!(
+            rows.iter().all(|row| row.len() == output_count),
+            "Every truth table row needs {} outputs",
+            output_count
+        );
+        Ok(Self {
+            input_count,
+            output_count,
+            rows,
+        })
+    }
+
+    /// Builds a table by evaluating `f` on every combination of inputs.
+    pub fn from_fn<F>(input_count: usize, mut f: F) -> anyhow::Result<Self>
+    where
+        F: FnMut(&[bool]) -> Vec<bool>,
+    {
+        ensure!(
+            input_count <= MAX_INPUTS,
+            "Truth tables are limited to {} inputs, got {}",
+            MAX_INPUTS,
+            input_count
+        );
+        let rows = (0..1u32 << input_count)
+            .map(|row| f(&row_inputs(row, input_count)))
+            .collect();
+        Self::new(input_count, rows)
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.input_count
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.output_count
+    }
+
+    pub fn get(&self, row: u32, output: usize) -> bool {
+        self.rows[row as usize][output]
+    }
+
+    /// The rows for which `output` is true.
+    pub fn minterms(&self, output: usize) -> Vec<u32> {
+        (0..self.rows.len() as u32)
+            .filter(|&row| self.get(row, output))
+            .collect()
+    }
+}
+
+fn row_inputs(row: u32, input_count: usize) -> Vec<bool> {
+    (0..input_count)
+        .map(|input| row & (1 << input) != 0)
+        .collect()
+}
+
+/// A product term: the inputs outside of `mask` must equal the corresponding bits of `value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Implicant {
+    pub value: u32,
+    /// Inputs that do not appear in the term.
+    pub mask: u32,
+}
+
+impl Implicant {
+    pub fn covers(&self, row: u32) -> bool {
+        row & !self.mask == self.value
+    }
+
+    /// The inputs appearing in the term, with whether they appear uncomplemented.
+    pub fn literals(&self, input_count: usize) -> impl Iterator<Item = (usize, bool)> + '_ {
+        (0..input_count)
+            .filter(move |&input| self.mask & (1 << input) == 0)
+            .map(move |input| (input, self.value & (1 << input) != 0))
+    }
+}
+
+/// Finds a small sum of products covering exactly `minterms`, using Quine-McCluskey.
+///
+/// All prime implicants are generated, then the essential ones are picked, and the remaining
+/// minterms are covered greedily. An empty result is the constant false; a single implicant with
+/// every input masked is the constant true.
+pub fn minimize(input_count: usize, minterms: &[u32]) -> Vec<Implicant> {
+    let mut current: Vec<Implicant> = minterms
+        .iter()
+        .map(|&value| Implicant { value, mask: 0 })
+        .collect();
+    current.sort_unstable();
+    current.dedup();
+
+    let mut primes = Vec::new();
+    while !current.is_empty() {
+        let mut combined = vec![false; current.len()];
+        let mut next = Vec::new();
+        for i in 0..current.len() {
+            for j in i + 1..current.len() {
+                let (a, b) = (current[i], current[j]);
+                let difference = a.value ^ b.value;
+                if a.mask == b.mask && difference.count_ones() == 1 {
+                    combined[i] = true;
+                    combined[j] = true;
+                    next.push(Implicant {
+                        value: a.value & !difference,
+                        mask: a.mask | difference,
+                    });
+                }
+            }
+        }
+        primes.extend(
+            current
+                .iter()
+                .zip(&combined)
+                .filter(|(_, &combined)| !combined)
+                .map(|(&implicant, _)| implicant),
+        );
+        next.sort_unstable();
+        next.dedup();
+        current = next;
+    }
+    debug_assert!(primes.iter().all(|prime| prime.mask < 1 << input_count));
+
+    let mut uncovered: Vec<u32> = minterms.to_vec();
+    uncovered.sort_unstable();
+    uncovered.dedup();
+    let mut cover = Vec::new();
+    // Essential primes are the only ones covering some minterm.
+    for &minterm in &uncovered {
+        let mut covering = primes.iter().filter(|prime| prime.covers(minterm));
+        if let (Some(&prime), None) = (covering.next(), covering.next()) {
+            if !cover.contains(&prime) {
+                cover.push(prime);
+            }
+        }
+    }
+    uncovered.retain(|&minterm| !cover.iter().any(|prime| prime.covers(minterm)));
+    while !uncovered.is_empty() {
+        let best = primes
+            .iter()
+            .copied()
+            .max_by_key(|prime| {
+                let covered = uncovered
+                    .iter()
+                    .filter(|&&minterm| prime.covers(minterm))
+                    .count();
+                // Prefer fewer literals among equally useful terms.
+                (covered, prime.mask.count_ones())
+            })
+            .expect("every minterm is covered by a prime implicant");
+        cover.push(best);
+        uncovered.retain(|&minterm| !best.covers(minterm));
+    }
+    cover.sort_unstable();
+    cover
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minimize, Implicant, TruthTable};
+
+    fn evaluate(cover: &[Implicant], row: u32) -> bool {
+        cover.iter().any(|implicant| implicant.covers(row))
+    }
+
+    #[test]
+    fn minimizes_basic_gates() {
+        let and = minimize(2, &[0b11]);
+        assert_eq!(
+            and,
+            vec![Implicant {
+                value: 0b11,
+                mask: 0
+            }]
+        );
+
+        let or = minimize(2, &[0b01, 0b10, 0b11]);
+        assert_eq!(or.len(), 2);
+        assert!(or.iter().all(|term| term.mask.count_ones() == 1));
+
+        let xor = minimize(2, &[0b01, 0b10]);
+        assert_eq!(xor.len(), 2);
+        assert!(xor.iter().all(|term| term.mask == 0));
+    }
+
+    #[test]
+    fn constants() {
+        assert!(minimize(3, &[]).is_empty());
+        assert_eq!(
+            minimize(2, &[0, 1, 2, 3]),
+            vec![Implicant {
+                value: 0,
+                mask: 0b11
+            }]
+        );
+    }
+
+    #[test]
+    fn covers_exactly_the_minterms() {
+        // A handful of arbitrary 4 and 6 input functions.
+        for (input_count, seed) in [(4, 0x6c1au64), (4, 0xbeef), (6, 0x0123_4567_89ab_cdef)] {
+            let table = TruthTable::from_fn(input_count, |inputs| {
+                let row = inputs
+                    .iter()
+                    .enumerate()
+                    .fold(0, |row, (index, &input)| row | (input as u32) << index);
+                vec![seed & (1 << row) != 0]
+            })
+            .unwrap();
+            let minterms = table.minterms(0);
+            let cover = minimize(input_count, &minterms);
+            for row in 0..1 << input_count {
+                assert_eq!(evaluate(&cover, row), table.get(row, 0), "row {:b}", row);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_tables() {
+        assert!(TruthTable::new(2, vec![vec![true]; 3]).is_err());
+        assert!(TruthTable::new(1, vec![vec![true], vec![true, false]]).is_err());
+        assert!(TruthTable::new(7, vec![vec![true]; 128]).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::rect::WireColor;
+use anyhow::Context;
+use glam::Vec4;
+use serde::Deserialize;
+use std::path::Path;
+
+const BUILTIN_NAMES: &[&str] = &["dark", "light", "solarized", "nord"];
+
+const BUILTIN_SOURCES: &[&str] = &[
+    include_str!("themes/dark.toml"),
+    include_str!("themes/light.toml"),
+    include_str!("themes/solarized.toml"),
+    include_str!("themes/nord.toml"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub clear_color: [f32; 4],
+    pub board_color: [f32; 4],
+    pub wire_color: WireColor,
+}
+
+impl Theme {
+    pub fn load_from_str(toml: &str) -> anyhow::Result<Self> {
+        let data: ThemeData = toml::from_str(toml).context("Failed to parse theme")?;
+        Ok(data.into())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        Self::load_from_str(&toml).with_context(|| format!("Invalid theme {}", path.display()))
+    }
+
+    /// The names of the themes compiled into the binary.
+    pub fn list_builtin() -> &'static [&'static str] {
+        BUILTIN_NAMES
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let index = BUILTIN_NAMES.iter().position(|&builtin| builtin == name)?;
+        Some(Self::load_from_str(BUILTIN_SOURCES[index]).expect("invalid builtin theme"))
+    }
+
+    /// Blends every color from `a` at `t = 0` to `b` at `t = 1`, for animated theme switches.
+    ///
+    /// The name is taken from `b`.
+    pub fn lerp(a: &Theme, b: &Theme, t: f32) -> Theme {
+        let t = t.clamp(0.0, 1.0);
+        let lerp_color = |a: [f32; 4], b: [f32; 4]| Vec4::from(a).lerp(Vec4::from(b), t).into();
+        Theme {
+            name: b.name.clone(),
+            clear_color: lerp_color(a.clear_color, b.clear_color),
+            board_color: lerp_color(a.board_color, b.board_color),
+            wire_color: WireColor::lerp(&a.wire_color, &b.wire_color, t),
+        }
+    }
+
+    /// All builtin themes, with any theme in `custom` replacing the builtin of the same name.
+    /// Custom themes that do not replace a builtin are added to the end.
+    pub fn all_with(custom: Option<Theme>) -> Vec<Self> {
+        let mut themes: Vec<Self> = BUILTIN_NAMES
+            .iter()
+            .map(|name| Self::builtin(name).unwrap())
+            .collect();
+        if let Some(custom) = custom {
+            match themes.iter_mut().find(|theme| theme.name == custom.name) {
+                Some(theme) => *theme = custom,
+                None => themes.push(custom),
+            }
+        }
+        themes
+    }
+}
+
+#[derive(Deserialize)]
+struct ThemeData {
+    name: String,
+    clear_color: [f32; 4],
+    board_color: [f32; 4],
+    wire: WireColorData,
+}
+
+#[derive(Deserialize)]
+struct WireColorData {
+    off_color: [f32; 4],
+    on_color: [f32; 4],
+    #[serde(default)]
+    gradient: bool,
+    off_end_color: Option<[f32; 4]>,
+    on_end_color: Option<[f32; 4]>,
+}
+
+impl From<ThemeData> for Theme {
+    fn from(data: ThemeData) -> Self {
+        let wire = data.wire;
+        Self {
+            name: data.name,
+            clear_color: data.clear_color,
+            board_color: data.board_color,
+            wire_color: WireColor {
+                off_color: Vec4::from(wire.off_color),
+                on_color: Vec4::from(wire.on_color),
+                gradient: wire.gradient,
+                off_end_color: Vec4::from(wire.off_end_color.unwrap_or(wire.off_color)),
+                on_end_color: Vec4::from(wire.on_end_color.unwrap_or(wire.on_color)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Theme;
+
+    #[test]
+    fn builtins_parse() {
+        for &name in Theme::list_builtin() {
+            let theme = Theme::builtin(name).unwrap();
+            assert_eq!(theme.name, name);
+        }
+    }
+
+    #[test]
+    fn custom_overrides_builtin() {
+        let custom = Theme::load_from_str(
+            r#"
+            name = "light"
+            clear_color = [1.0, 1.0, 1.0, 1.0]
+            board_color = [1.0, 1.0, 1.0, 1.0]
+
+            [wire]
+            off_color = [0.0, 0.0, 0.0, 1.0]
+            on_color = [0.0, 1.0, 0.0, 1.0]
+            "#,
+        )
+        .unwrap();
+        let themes = Theme::all_with(Some(custom.clone()));
+        assert_eq!(themes.len(), Theme::list_builtin().len());
+        assert!(themes.contains(&custom));
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let dark = Theme::builtin("dark").unwrap();
+        let light = Theme::builtin("light").unwrap();
+        assert_eq!(Theme::lerp(&dark, &light, 0.0).wire_color, dark.wire_color);
+        assert_eq!(Theme::lerp(&dark, &light, 1.0), light);
+
+        let half = Theme::lerp(&dark, &light, 0.5);
+        let expected = (dark.wire_color.on_color + light.wire_color.on_color) / 2.0;
+        assert!(half.wire_color.on_color.abs_diff_eq(expected, 1e-6));
+    }
+
+    #[test]
+    fn missing_field() {
+        assert!(Theme::load_from_str("name = \"broken\"").is_err());
+    }
+}
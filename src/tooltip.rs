@@ -0,0 +1,112 @@
+use glam::Vec2;
+
+/// How long the cursor has to rest over an item before its tooltip appears, in seconds.
+pub const DEFAULT_DELAY: f32 = 0.5;
+/// How long the tooltip takes to fade in, in seconds.
+pub const DEFAULT_FADE_IN: f32 = 0.2;
+
+/// Offset from the cursor to the top-left corner of the tooltip, in pixels.
+const CURSOR_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+/// A text box that appears next to the cursor after it has rested over an item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tooltip {
+    pub content: String,
+    pub screen_position: Vec2,
+    pub visible: bool,
+    pub opacity: f32,
+    pub delay: f32,
+    pub fade_in: f32,
+    hover_time: f32,
+}
+
+impl Default for Tooltip {
+    fn default() -> Self {
+        Self {
+            content: String::new(),
+            screen_position: Vec2::ZERO,
+            visible: false,
+            opacity: 0.0,
+            delay: DEFAULT_DELAY,
+            fade_in: DEFAULT_FADE_IN,
+            hover_time: 0.0,
+        }
+    }
+}
+
+impl Tooltip {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advances the show/hide state by `dt` seconds.
+    ///
+    /// Moving the cursor restarts the delay until the tooltip is shown; once shown it stays until
+    /// nothing is hovered anymore.
+    pub fn update(&mut self, cursor_moved: bool, hovered: bool, dt: f32) {
+        if !hovered {
+            self.visible = false;
+            self.opacity = 0.0;
+            self.hover_time = 0.0;
+            return;
+        }
+        if cursor_moved && !self.visible {
+            self.hover_time = 0.0;
+            return;
+        }
+        self.hover_time += dt;
+        if self.hover_time >= self.delay {
+            self.visible = true;
+            self.opacity = if self.fade_in > 0.0 {
+                ((self.hover_time - self.delay) / self.fade_in).min(1.0)
+            } else {
+                1.0
+            };
+        }
+    }
+
+    /// Where to draw a tooltip of `size` pixels next to `cursor`, moved so that it fits entirely
+    /// inside a window of `window_size` pixels where possible.
+    pub fn clamped_position(cursor: Vec2, size: Vec2, window_size: Vec2) -> Vec2 {
+        let position = cursor + CURSOR_OFFSET;
+        position.min(window_size - size).max(Vec2::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tooltip;
+    use glam::Vec2;
+
+    #[test]
+    fn fades_in_after_delay() {
+        let mut tooltip = Tooltip::new();
+        tooltip.update(false, true, 0.4);
+        assert!(!tooltip.visible);
+        tooltip.update(true, true, 0.4);
+        tooltip.update(false, true, 0.4);
+        assert!(!tooltip.visible);
+        tooltip.update(false, true, 0.2);
+        assert!(tooltip.visible);
+        assert!((tooltip.opacity - 0.5).abs() < 1e-5);
+        tooltip.update(true, true, 1.0);
+        assert_eq!(tooltip.opacity, 1.0);
+        tooltip.update(false, false, 0.1);
+        assert!(!tooltip.visible);
+        assert_eq!(tooltip.opacity, 0.0);
+    }
+
+    #[test]
+    fn stays_inside_window() {
+        let window = Vec2::new(800.0, 600.0);
+        let size = Vec2::new(100.0, 20.0);
+        assert_eq!(
+            Tooltip::clamped_position(Vec2::new(10.0, 10.0), size, window),
+            Vec2::new(26.0, 26.0)
+        );
+        assert_eq!(
+            Tooltip::clamped_position(Vec2::new(790.0, 595.0), size, window),
+            Vec2::new(700.0, 580.0)
+        );
+    }
+}
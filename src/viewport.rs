@@ -1,34 +1,95 @@
+use crate::circuit::Circuit;
 use crate::GraphicsContext;
+use anyhow::Context;
 use bytemuck::{Pod, Zeroable};
 use glam::{IVec2, Mat4, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
 use std::time::Duration;
 use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
 
+/// How quickly [`Camera::pan_to`] closes the distance to its target, per second.
+const PAN_SMOOTHING: f32 = 8.0;
+/// Multiplies the pan speed while panning fast (Shift held).
+pub const FAST_PAN_FACTOR: f32 = 3.0;
+/// Multiplies the pan speed while panning slowly (Ctrl held).
+pub const SLOW_PAN_FACTOR: f32 = 1.0 / 3.0;
+/// How long the camera waits after a manual pan before following its target again.
+const FOLLOW_RESUME_DELAY: Duration = Duration::from_secs(2);
+
+/// The view transform and its controls.
+///
+/// Serialization keeps the view, its settings and the followed tile; inputs and animations in
+/// progress are skipped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Camera {
     pub pan: Vec2,
     pub zoom: f32,
+    /// Counter-clockwise rotation of the view, in radians. See [`Self::orbit`].
+    pub view_rotation: f32,
 
+    /// Keyboard and gamepad pan rate, in pixels per second.
     pub pan_speed: f32,
+    /// Temporarily scales `pan_speed`, see [`FAST_PAN_FACTOR`] and [`SLOW_PAN_FACTOR`].
+    #[serde(skip)]
+    pub pan_speed_factor: f32,
     pub zoom_speed: f32,
     pub zoom_step: f32,
     pub min_zoom: f32,
     pub max_zoom: f32,
 
+    #[serde(skip)]
     pub pan_up: bool,
+    #[serde(skip)]
     pub pan_down: bool,
+    #[serde(skip)]
     pub pan_left: bool,
+    #[serde(skip)]
     pub pan_right: bool,
+    #[serde(skip)]
     pub zoom_in: bool,
+    #[serde(skip)]
     pub zoom_out: bool,
+    /// Analog pan input, e.g. from a gamepad stick, in units of `pan_speed`.
+    #[serde(skip)]
+    pub analog_pan: Vec2,
+    /// Analog zoom input, in units of `zoom_speed`. Positive zooms in.
+    #[serde(skip)]
+    pub analog_zoom: f32,
+
+    #[serde(skip)]
+    pan_target: Option<Vec2>,
+    follow_target: Option<IVec2>,
+    #[serde(skip)]
+    since_manual_pan: Duration,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
+    /// Loads camera settings from a TOML file. Missing fields keep their default values.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read camera settings {}", path.display()))?;
+        toml::from_str(&toml).with_context(|| format!("Invalid camera settings {}", path.display()))
+    }
+
     fn new() -> Self {
         Self {
             pan: Vec2::ZERO,
             zoom: 16.0,
+            view_rotation: 0.0,
 
             pan_speed: 500.0,
+            pan_speed_factor: 1.0,
             zoom_speed: 4.0,
             zoom_step: 1.1,
             min_zoom: 8.0,
@@ -40,6 +101,12 @@ impl Camera {
             pan_right: false,
             zoom_in: false,
             zoom_out: false,
+            analog_pan: Vec2::ZERO,
+            analog_zoom: 0.0,
+
+            pan_target: None,
+            follow_target: None,
+            since_manual_pan: FOLLOW_RESUME_DELAY,
         }
     }
 
@@ -58,7 +125,29 @@ impl Camera {
         if self.pan_left {
             pan_delta -= Vec2::X;
         }
-        self.pan += dt * self.pan_speed / self.zoom * pan_delta;
+        pan_delta += self.analog_pan;
+        if pan_delta != Vec2::ZERO {
+            // Pan along the screen axes, whichever way the view is rotated.
+            let speed = self.pan_speed * self.pan_speed_factor;
+            self.pan += dt * speed / self.zoom * self.rotation().rotate(pan_delta);
+            self.manual_pan();
+        } else {
+            self.since_manual_pan += Duration::from_secs_f32(dt);
+        }
+
+        if let Some(target) = self.follow_target {
+            if self.since_manual_pan >= FOLLOW_RESUME_DELAY {
+                self.pan_to(target.as_vec2() + Vec2::splat(0.5));
+            }
+        }
+        if let Some(target) = self.pan_target {
+            self.pan = self.pan.lerp(target, 1.0 - (-PAN_SMOOTHING * dt).exp());
+            // Stop once the remaining distance is below a pixel.
+            if self.pan.distance(target) * self.zoom < 1.0 {
+                self.pan = target;
+                self.pan_target = None;
+            }
+        }
 
         let mut zoom_factor = 1.0;
         if self.zoom_in {
@@ -67,12 +156,83 @@ impl Camera {
         if self.zoom_out {
             zoom_factor /= self.zoom_speed;
         }
+        zoom_factor *= self.zoom_speed.powf(self.analog_zoom);
         self.set_zoom(self.zoom * zoom_factor.powf(dt));
     }
 
     pub fn set_zoom(&mut self, zoom: f32) {
         self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
     }
+
+    /// Sets the zoom like [`Self::set_zoom`], panning so that the world position under
+    /// `screen_position` stays there, for a window of `view_size`.
+    pub fn zoom_at(&mut self, screen_position: Vec2, view_size: Vec2, zoom: f32) {
+        let before = self.screen_to_world(screen_position, view_size);
+        self.set_zoom(zoom);
+        let after = self.screen_to_world(screen_position, view_size);
+        if before != after {
+            self.pan += before - after;
+            self.manual_pan();
+        }
+    }
+
+    /// Moves the camera so the world follows a drag of `delta` pixels, in screen coordinates
+    /// (Y pointing down).
+    pub fn pan_by_screen_delta(&mut self, delta: Vec2) {
+        self.pan -= self.rotation().rotate(delta * Vec2::new(1.0, -1.0)) / self.zoom;
+        self.manual_pan();
+    }
+
+    /// Rotates the camera counter-clockwise by `angle_rad` around the world position `center`,
+    /// turning the view with it so that `center` stays at the same place on screen.
+    pub fn orbit(&mut self, center: Vec2, angle_rad: f32) {
+        self.pan = center + Vec2::from_angle(angle_rad).rotate(self.pan - center);
+        self.view_rotation = (self.view_rotation + angle_rad).rem_euclid(std::f32::consts::TAU);
+        self.manual_pan();
+    }
+
+    /// Maps view directions to world directions.
+    fn rotation(&self) -> Vec2 {
+        Vec2::from_angle(self.view_rotation)
+    }
+
+    /// Smoothly moves the camera so `target` ends up in the center of the view.
+    pub fn pan_to(&mut self, target: Vec2) {
+        self.pan_target = Some(target);
+    }
+
+    /// Keeps the tile `target` centered while `enabled`.
+    ///
+    /// Panning manually overrides this, until the camera has been left alone for
+    /// [`FOLLOW_RESUME_DELAY`].
+    pub fn follow(&mut self, target: IVec2, enabled: bool) {
+        self.follow_target = enabled.then_some(target);
+        if !enabled {
+            self.pan_target = None;
+        }
+    }
+
+    /// The tile the camera is following, if any.
+    pub fn follow_target(&self) -> Option<IVec2> {
+        self.follow_target
+    }
+
+    /// Converts window coordinates, in pixels, to a world position for a window of `view_size`.
+    pub fn screen_to_world(&self, screen_position: Vec2, view_size: Vec2) -> Vec2 {
+        let view_offset = (screen_position - view_size / 2.0) * Vec2::new(1.0, -1.0) / self.zoom;
+        self.rotation().rotate(view_offset) + self.pan
+    }
+
+    /// Converts a world position to window coordinates, in pixels, for a window of `view_size`.
+    pub fn world_to_screen(&self, world_position: Vec2, view_size: Vec2) -> Vec2 {
+        let view_offset = Vec2::from_angle(-self.view_rotation).rotate(world_position - self.pan);
+        view_offset * self.zoom * Vec2::new(1.0, -1.0) + view_size / 2.0
+    }
+
+    fn manual_pan(&mut self) {
+        self.pan_target = None;
+        self.since_manual_pan = Duration::ZERO;
+    }
 }
 
 pub struct Cursor {
@@ -88,18 +248,26 @@ impl Cursor {
         }
     }
 
-    fn update(&mut self, gfx: &GraphicsContext, camera: &Camera) {
-        let size = Vec2::new(
-            gfx.window.inner_size().width as f32,
-            gfx.window.inner_size().height as f32,
-        );
-        self.world_position =
-            (self.screen_position - size / 2.0) * Vec2::new(1.0, -1.0) / camera.zoom + camera.pan;
+    fn update(&mut self, camera: &Camera, view_size: Vec2) {
+        self.world_position = camera.screen_to_world(self.screen_position, view_size);
     }
 
     pub fn tile(&self) -> IVec2 {
         self.world_position.floor().as_ivec2()
     }
+
+    /// The world-space center of the tile under the cursor.
+    pub fn tile_center(&self) -> Vec2 {
+        self.tile().as_vec2() + Vec2::splat(0.5)
+    }
+
+    /// Whether the cursor is over a component, with the hit margin of
+    /// [`Circuit::component_at_screen`].
+    pub fn is_over_component(&self, circuit: &Circuit, viewport: &Viewport) -> bool {
+        circuit
+            .component_at_screen(self.screen_position, viewport)
+            .is_some()
+    }
 }
 
 pub struct Viewport {
@@ -109,6 +277,11 @@ pub struct Viewport {
     bind_group: wgpu::BindGroup,
     camera: Camera,
     cursor: Cursor,
+    /// The latest cursor position reported by the window, applied by [`Self::begin_frame`].
+    pending_cursor_position: Vec2,
+    size: Vec2,
+    /// Set when the camera or the size changed, so [`Self::update`] must upload the uniforms.
+    dirty: bool,
 }
 
 impl Viewport {
@@ -151,26 +324,56 @@ impl Viewport {
             bind_group,
             camera: Camera::new(),
             cursor: Cursor::new(),
+            pending_cursor_position: Vec2::ZERO,
+            size: physical_size_to_vec(gfx.window.inner_size()),
+            dirty: true,
         }
     }
 
+    /// Updates everything that depends on the window size. Must be called whenever the window is
+    /// resized.
+    pub fn on_resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.size = physical_size_to_vec(new_size);
+        self.dirty = true;
+    }
+
+    /// The size of the view, in pixels.
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Moves the camera, and uploads the view uniforms if anything changed since the last
+    /// upload.
     pub fn update(&mut self, dt: Duration) {
+        let view = (self.camera.pan, self.camera.zoom, self.camera.view_rotation);
         self.camera.update(dt);
-        self.cursor.update(&self.gfx, &self.camera);
+        if self.dirty || view != (self.camera.pan, self.camera.zoom, self.camera.view_rotation) {
+            self.sync();
+        }
+    }
 
-        let size = Vec2::new(
-            self.gfx.window.inner_size().width as f32,
-            self.gfx.window.inner_size().height as f32,
-        );
+    /// Recomputes camera-dependent state and uploads the view uniforms.
+    fn sync(&mut self) {
+        self.dirty = false;
+        self.cursor.update(&self.camera, self.size);
         self.gfx.queue.write_buffer(
             &self.uniform_buffer,
             0,
-            bytemuck::bytes_of(&Uniforms::new(size, &self.camera)),
+            bytemuck::bytes_of(&Uniforms::new(self.size, &self.camera)),
         );
     }
 
+    /// Records the cursor position, in pixels. [`Self::cursor`] keeps reporting the previous
+    /// position until the next [`Self::begin_frame`].
     pub fn cursor_moved(&mut self, position: Vec2) {
-        self.cursor.screen_position = position;
+        self.pending_cursor_position = position;
+    }
+
+    /// Moves the cursor to the position last given to [`Self::cursor_moved`], updating its
+    /// screen and world positions together. Must be called at the start of every frame.
+    pub fn begin_frame(&mut self) {
+        self.cursor.screen_position = self.pending_cursor_position;
+        self.cursor.update(&self.camera, self.size);
     }
 
     pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
@@ -181,15 +384,75 @@ impl Viewport {
         &self.bind_group
     }
 
-    pub fn camera_mut(&mut self) -> &mut Camera {
-        &mut self.camera
+    /// Borrows the camera for modification. The changes are uploaded by the next
+    /// [`Self::update`].
+    pub fn camera_mut(&mut self) -> CameraGuard<'_> {
+        CameraGuard { viewport: self }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Zooms by `delta` steps of [`Camera::zoom_step`] around `screen_position`, in pixels, see
+    /// [`Camera::zoom_at`]. Positive zooms in.
+    pub fn zoom_at(&mut self, screen_position: Vec2, delta: f32) {
+        let size = self.size;
+        let mut camera = self.camera_mut();
+        let zoom = camera.zoom * camera.zoom_step.powf(delta);
+        camera.zoom_at(screen_position, size, zoom);
+    }
+
+    /// Sets the keyboard and gamepad pan rate, in pixels per second.
+    pub fn set_pan_speed(&mut self, speed: f32) {
+        self.camera.pan_speed = speed.max(0.0);
+    }
+
+    /// Converts window coordinates, in pixels, to a world position.
+    pub fn screen_to_world(&self, screen_position: Vec2) -> Vec2 {
+        self.camera.screen_to_world(screen_position, self.size)
     }
 
+    /// Converts a world position to window coordinates, in pixels.
+    pub fn world_to_screen(&self, world_position: Vec2) -> Vec2 {
+        self.camera.world_to_screen(world_position, self.size)
+    }
+
+    /// The cursor as of the start of the frame; its screen position, world position and tile
+    /// always agree.
     pub fn cursor(&self) -> &Cursor {
         &self.cursor
     }
 }
 
+fn physical_size_to_vec(size: PhysicalSize<u32>) -> Vec2 {
+    Vec2::new(size.width as f32, size.height as f32)
+}
+
+pub struct CameraGuard<'a> {
+    viewport: &'a mut Viewport,
+}
+
+impl<'a> Deref for CameraGuard<'a> {
+    type Target = Camera;
+
+    fn deref(&self) -> &Self::Target {
+        &self.viewport.camera
+    }
+}
+
+impl<'a> DerefMut for CameraGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.viewport.camera
+    }
+}
+
+impl<'a> Drop for CameraGuard<'a> {
+    fn drop(&mut self) {
+        self.viewport.dirty = true;
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 struct Uniforms {
@@ -215,6 +478,7 @@ impl Uniforms {
             Vec4::W,
         );
         let view = Mat4::from_scale(Vec3::new(camera.zoom, camera.zoom, 1.0))
+            * Mat4::from_rotation_z(-camera.view_rotation)
             * Mat4::from_translation(-camera.pan.extend(0.0));
         Self {
             view_proj: (proj * view).to_cols_array_2d(),
@@ -223,3 +487,139 @@ impl Uniforms {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Camera, FOLLOW_RESUME_DELAY};
+    use glam::{IVec2, Vec2};
+    use std::time::Duration;
+
+    #[test]
+    fn pan_by_screen_delta_round_trip() {
+        let mut camera = Camera::new();
+        camera.pan = Vec2::new(3.0, -2.0);
+        camera.pan_by_screen_delta(Vec2::new(10.0, 0.0));
+        assert_eq!(camera.pan, Vec2::new(3.0 - 10.0 / camera.zoom, -2.0));
+        camera.pan_by_screen_delta(Vec2::new(-10.0, 0.0));
+        assert_eq!(camera.pan, Vec2::new(3.0, -2.0));
+    }
+
+    #[test]
+    fn zoom_at_keeps_point_fixed() {
+        let mut camera = Camera::new();
+        camera.pan = Vec2::new(3.0, -2.0);
+        let view_size = Vec2::new(800.0, 600.0);
+        let cursor = Vec2::new(100.0, 500.0);
+        let before = camera.screen_to_world(cursor, view_size);
+        camera.zoom_at(cursor, view_size, camera.zoom * 2.0);
+        assert_eq!(camera.zoom, 32.0);
+        assert!(camera
+            .screen_to_world(cursor, view_size)
+            .abs_diff_eq(before, 1e-4));
+
+        // Zooming at the center does not pan.
+        let pan = camera.pan;
+        camera.zoom_at(view_size / 2.0, view_size, camera.zoom / 2.0);
+        assert_eq!(camera.pan, pan);
+    }
+
+    #[test]
+    fn pan_by_screen_delta_flips_y() {
+        let mut camera = Camera::new();
+        camera.pan_by_screen_delta(Vec2::new(0.0, camera.zoom));
+        assert_eq!(camera.pan, Vec2::Y);
+    }
+
+    #[test]
+    fn follow_resumes_after_manual_pan() {
+        let mut camera = Camera::new();
+        camera.follow(IVec2::new(10, 4), true);
+        for _ in 0..100 {
+            camera.update(Duration::from_millis(16));
+        }
+        assert_eq!(camera.pan, Vec2::new(10.5, 4.5));
+
+        camera.pan_by_screen_delta(Vec2::new(100.0, 0.0));
+        let panned = camera.pan;
+        camera.update(FOLLOW_RESUME_DELAY / 2);
+        assert_eq!(camera.pan, panned);
+
+        for _ in 0..200 {
+            camera.update(Duration::from_millis(16));
+        }
+        assert_eq!(camera.pan, Vec2::new(10.5, 4.5));
+
+        camera.follow(IVec2::ZERO, false);
+        camera.update(FOLLOW_RESUME_DELAY);
+        assert_eq!(camera.pan, Vec2::new(10.5, 4.5));
+    }
+
+    #[test]
+    fn world_to_screen_after_resize() {
+        let mut camera = Camera::new();
+        camera.pan = Vec2::new(2.0, 3.0);
+        let world = Vec2::new(4.0, 1.0);
+        for size in [Vec2::new(800.0, 600.0), Vec2::new(1920.0, 1080.0)] {
+            // The camera center stays in the middle of the window.
+            assert_eq!(camera.world_to_screen(camera.pan, size), size / 2.0);
+            let screen = camera.world_to_screen(world, size);
+            assert_eq!(
+                screen,
+                size / 2.0 + Vec2::new(2.0, 2.0) * camera.zoom,
+                "view size {:?}",
+                size
+            );
+            assert_eq!(camera.screen_to_world(screen, size), world);
+        }
+    }
+
+    #[test]
+    fn orbit_keeps_center_on_screen() {
+        let size = Vec2::new(800.0, 600.0);
+        let mut camera = Camera::new();
+        camera.pan = Vec2::new(2.0, 3.0);
+        let center = Vec2::new(5.0, -1.0);
+        let center_on_screen = camera.world_to_screen(center, size);
+
+        camera.orbit(center, std::f32::consts::FRAC_PI_2);
+        assert!(camera.pan.abs_diff_eq(Vec2::new(1.0, -4.0), 1e-5));
+        assert!(camera
+            .world_to_screen(center, size)
+            .abs_diff_eq(center_on_screen, 1e-3));
+        let screen = Vec2::new(123.0, 456.0);
+        assert!(camera
+            .world_to_screen(camera.screen_to_world(screen, size), size)
+            .abs_diff_eq(screen, 1e-3));
+
+        // Dragging right still moves the world right on screen.
+        let before = camera.world_to_screen(center, size);
+        camera.pan_by_screen_delta(Vec2::new(10.0, 0.0));
+        assert!(camera
+            .world_to_screen(center, size)
+            .abs_diff_eq(before + Vec2::new(10.0, 0.0), 1e-3));
+    }
+
+    #[test]
+    fn serde_skips_transient_state() {
+        let mut camera = Camera::new();
+        camera.pan = Vec2::new(1.5, -2.0);
+        camera.set_zoom(32.0);
+        camera.follow(IVec2::new(3, 4), true);
+        camera.pan_left = true;
+        camera.pan_to(Vec2::ZERO);
+
+        let json = serde_json::to_string(&camera).unwrap();
+        assert!(!json.contains("pan_left"));
+        let restored: Camera = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.pan, camera.pan);
+        assert_eq!(restored.zoom, 32.0);
+        assert_eq!(restored.follow_target(), Some(IVec2::new(3, 4)));
+        assert!(!restored.pan_left);
+        assert_eq!(restored.pan_speed_factor, 1.0);
+        assert_eq!(restored.pan_target, None);
+
+        let partial: Camera = toml::from_str("zoom = 20.0").unwrap();
+        assert_eq!(partial.zoom, 20.0);
+        assert_eq!(partial.pan, Vec2::ZERO);
+    }
+}